@@ -0,0 +1,231 @@
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::{
+    error::{Error, ErrorKind, Note},
+    token::TokenInfo,
+};
+
+#[derive(Serialize)]
+struct JsonSpan<'a> {
+    message: &'a str,
+    line: usize,
+    column: usize,
+    length: usize,
+}
+
+#[derive(Serialize)]
+struct JsonNote<'a> {
+    severity: &'static str,
+    message: &'a str,
+}
+
+// Lines and columns are one-based, matching the human-readable output.
+#[derive(Serialize)]
+struct JsonDiagnostic<'a> {
+    severity: &'static str,
+    code: &'static str,
+    kind: ErrorKind,
+    message: &'a str,
+    file: &'a str,
+    line: usize,
+    column: usize,
+    length: usize,
+    labels: Vec<JsonSpan<'a>>,
+    notes: Vec<JsonNote<'a>>,
+}
+
+pub struct Emitter<'a> {
+    source: &'a str,
+    path: &'a str,
+}
+
+impl<'a> Emitter<'a> {
+    pub fn new(source: &'a str, path: &'a str) -> Self {
+        Self { source, path }
+    }
+
+    pub fn render(&self, error: &Error) -> String {
+        let mut out = String::new();
+        writeln!(out, "{}[{}]: {}", error.kind(), error.code, error.message).unwrap();
+
+        let info = &error.info;
+        let width = error
+            .labels
+            .iter()
+            .map(|label| label.info.line)
+            .chain([info.line])
+            .map(|line| (line + 1).to_string().len())
+            .max()
+            .unwrap_or_default();
+        let gutter = " ".repeat(width);
+
+        writeln!(
+            out,
+            "{}--> {}:{}:{}",
+            gutter,
+            self.path,
+            info.line + 1,
+            info.column + 1
+        )
+        .unwrap();
+        self.snippet(&mut out, &gutter, info, "");
+        for label in &error.labels {
+            self.snippet(&mut out, &gutter, &label.info, &label.message);
+        }
+
+        for note in &error.notes {
+            match note {
+                Note::Note(note) => writeln!(out, "{} = note: {}", gutter, note).unwrap(),
+                Note::Help(help) => writeln!(out, "{} = help: {}", gutter, help).unwrap(),
+            }
+        }
+
+        out
+    }
+
+    pub fn render_json(&self, error: &Error) -> String {
+        let diagnostic = JsonDiagnostic {
+            severity: match error.kind() {
+                ErrorKind::Warning => "warning",
+                _ => "error",
+            },
+            code: error.code.as_str(),
+            kind: error.kind(),
+            message: &error.message,
+            file: self.path,
+            line: error.info.line + 1,
+            column: error.info.column + 1,
+            length: error.info.length,
+            labels: error
+                .labels
+                .iter()
+                .map(|label| JsonSpan {
+                    message: &label.message,
+                    line: label.info.line + 1,
+                    column: label.info.column + 1,
+                    length: label.info.length,
+                })
+                .collect(),
+            notes: error
+                .notes
+                .iter()
+                .map(|note| match note {
+                    Note::Note(message) => JsonNote {
+                        severity: "note",
+                        message,
+                    },
+                    Note::Help(message) => JsonNote {
+                        severity: "help",
+                        message,
+                    },
+                })
+                .collect(),
+        };
+        serde_json::to_string(&diagnostic).unwrap()
+    }
+
+    fn snippet(&self, out: &mut String, gutter: &str, info: &TokenInfo, message: &str) {
+        let line = self.line(info.line);
+        let underline = format!(
+            "{}{} {}",
+            self.padding(line, info.column),
+            self.underline(line, info.column, info.start, info.length),
+            message
+        );
+
+        writeln!(out, "{} |", gutter).unwrap();
+        writeln!(
+            out,
+            "{:>width$} | {}",
+            info.line + 1,
+            line,
+            width = gutter.len()
+        )
+        .unwrap();
+        writeln!(out, "{} | {}", gutter, underline.trim_end()).unwrap();
+    }
+
+    fn line(&self, line: usize) -> &'a str {
+        self.source
+            .split('\n')
+            .nth(line)
+            .unwrap_or_default()
+            .trim_end_matches('\r')
+    }
+
+    // Keep tabs in the padding so the caret lines up with the source line.
+    fn padding(&self, line: &str, column: usize) -> String {
+        let mut chars = line.chars();
+        (0..column)
+            .map(|_| match chars.next() {
+                Some('\t') => '\t',
+                _ => ' ',
+            })
+            .collect()
+    }
+
+    fn underline(&self, line: &str, column: usize, start: usize, length: usize) -> String {
+        let span = self
+            .source
+            .get(start..start + length)
+            .unwrap_or_default()
+            .chars()
+            .count();
+        let remaining = line.chars().count().saturating_sub(column);
+        let width = span.min(remaining).max(1);
+
+        format!("^{}", "~".repeat(width - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{interpreter::Interpreter, parser::Parser, scanner::Scanner};
+    use insta::assert_snapshot;
+
+    fn render(source: &str) -> String {
+        let emitter = Emitter::new(source, "test.c");
+        let errors = Scanner::new(source.to_string())
+            .scan()
+            .and_then(|tokens| Parser::new(&tokens).parse())
+            .unwrap_err();
+        emitter.render(&errors[0])
+    }
+
+    #[test]
+    fn test_underline_token() {
+        assert_snapshot!(render("1 + 2\n3 * 4.5.6"));
+    }
+
+    #[test]
+    fn test_end_of_input() {
+        assert_snapshot!(render("(1 + 2"));
+    }
+
+    #[test]
+    fn test_runtime_labels() {
+        let source = "1 +\n(2 / (3 - 3))";
+        let tokens = Scanner::new(source.to_string()).scan().unwrap();
+        let chunk = Parser::new(&tokens).parse().unwrap();
+        let error = Interpreter::new(&chunk).interpret().unwrap_err();
+        assert_snapshot!(Emitter::new(source, "test.c").render(&error));
+    }
+
+    #[test]
+    fn test_json() {
+        let source = "(1 + 2";
+        let errors = Scanner::new(source.to_string())
+            .scan()
+            .and_then(|tokens| Parser::new(&tokens).parse())
+            .unwrap_err();
+        assert_snapshot!(Emitter::new(source, "test.c").render_json(&errors[0]));
+    }
+
+    #[test]
+    fn test_tabs() {
+        assert_snapshot!(render("\t1 +\t$"));
+    }
+}
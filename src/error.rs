@@ -0,0 +1,267 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::token::TokenInfo;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorKind {
+    Lexical,
+    Syntax,
+    Semantic,
+    Runtime,
+    Warning,
+}
+
+// Codes are part of the machine-readable output, so existing ones must never
+// be renumbered: add new codes at the end of their range instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorCode {
+    UnexpectedCharacter,
+    MalformedNumber,
+    IntegerTooLarge,
+    UnterminatedComment,
+    InvalidSuffix,
+    InvalidDigit,
+    UnterminatedLiteral,
+    InvalidEscape,
+    InvalidCharConstant,
+    ExpectedExpression,
+    ExpectedToken,
+    UnexpectedOperator,
+    ExpectedOperator,
+    InvalidTypeName,
+    InvalidOperandTypes,
+    ShiftCountOutOfRange,
+    NotAssignable,
+    UndeclaredIdentifier,
+    NotConstant,
+    IncompleteType,
+    NoSuchMember,
+    InvalidType,
+    Redeclaration,
+    InvalidStorageClass,
+    UndefinedReference,
+    NotInLoop,
+    StackUnderflow,
+    IntegerOverflow,
+    DivisionByZero,
+    InvalidOperands,
+    InvalidShift,
+    NarrowingConversion,
+    Uninitialized,
+    AssignmentAsCondition,
+}
+
+impl ErrorCode {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ErrorCode::UnexpectedCharacter
+            | ErrorCode::MalformedNumber
+            | ErrorCode::IntegerTooLarge
+            | ErrorCode::UnterminatedComment
+            | ErrorCode::InvalidSuffix
+            | ErrorCode::InvalidDigit
+            | ErrorCode::UnterminatedLiteral
+            | ErrorCode::InvalidEscape
+            | ErrorCode::InvalidCharConstant => ErrorKind::Lexical,
+            ErrorCode::ExpectedExpression
+            | ErrorCode::ExpectedToken
+            | ErrorCode::UnexpectedOperator
+            | ErrorCode::ExpectedOperator
+            | ErrorCode::InvalidTypeName => ErrorKind::Syntax,
+            ErrorCode::InvalidOperandTypes
+            | ErrorCode::ShiftCountOutOfRange
+            | ErrorCode::NotAssignable
+            | ErrorCode::UndeclaredIdentifier
+            | ErrorCode::NotConstant
+            | ErrorCode::IncompleteType
+            | ErrorCode::NoSuchMember
+            | ErrorCode::InvalidType
+            | ErrorCode::Redeclaration
+            | ErrorCode::InvalidStorageClass
+            | ErrorCode::UndefinedReference
+            | ErrorCode::NotInLoop => ErrorKind::Semantic,
+            ErrorCode::StackUnderflow
+            | ErrorCode::IntegerOverflow
+            | ErrorCode::DivisionByZero
+            | ErrorCode::InvalidOperands
+            | ErrorCode::InvalidShift => ErrorKind::Runtime,
+            ErrorCode::NarrowingConversion
+            | ErrorCode::Uninitialized
+            | ErrorCode::AssignmentAsCondition => ErrorKind::Warning,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedCharacter => "E0001",
+            ErrorCode::MalformedNumber => "E0002",
+            ErrorCode::IntegerTooLarge => "E0003",
+            ErrorCode::UnterminatedComment => "E0004",
+            ErrorCode::InvalidSuffix => "E0005",
+            ErrorCode::InvalidDigit => "E0006",
+            ErrorCode::UnterminatedLiteral => "E0007",
+            ErrorCode::InvalidEscape => "E0008",
+            ErrorCode::InvalidCharConstant => "E0009",
+            ErrorCode::ExpectedExpression => "E0100",
+            ErrorCode::ExpectedToken => "E0101",
+            ErrorCode::UnexpectedOperator => "E0102",
+            ErrorCode::ExpectedOperator => "E0103",
+            ErrorCode::InvalidTypeName => "E0104",
+            ErrorCode::InvalidOperandTypes => "E0300",
+            ErrorCode::ShiftCountOutOfRange => "E0301",
+            ErrorCode::NotAssignable => "E0302",
+            ErrorCode::UndeclaredIdentifier => "E0303",
+            ErrorCode::NotConstant => "E0304",
+            ErrorCode::IncompleteType => "E0305",
+            ErrorCode::NoSuchMember => "E0306",
+            ErrorCode::InvalidType => "E0307",
+            ErrorCode::Redeclaration => "E0308",
+            ErrorCode::InvalidStorageClass => "E0309",
+            ErrorCode::UndefinedReference => "E0310",
+            ErrorCode::NotInLoop => "E0311",
+            ErrorCode::StackUnderflow => "E0200",
+            ErrorCode::IntegerOverflow => "E0201",
+            ErrorCode::DivisionByZero => "E0202",
+            ErrorCode::InvalidOperands => "E0203",
+            ErrorCode::InvalidShift => "E0204",
+            // E0205 was a use of an uninitialized variable, which now reads
+            // as zero. It stays reserved.
+            ErrorCode::NarrowingConversion => "W0001",
+            ErrorCode::Uninitialized => "W0002",
+            ErrorCode::AssignmentAsCondition => "W0003",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedCharacter => "Unexpected character",
+            ErrorCode::MalformedNumber => "Malformed number literal",
+            ErrorCode::IntegerTooLarge => "Integer literal is too large",
+            ErrorCode::UnterminatedComment => "Unterminated block comment",
+            ErrorCode::InvalidSuffix => "Invalid suffix on numeric constant",
+            ErrorCode::InvalidDigit => "Invalid digit in numeric constant",
+            ErrorCode::UnterminatedLiteral => "Unterminated character or string literal",
+            ErrorCode::InvalidEscape => "Invalid escape sequence",
+            ErrorCode::InvalidCharConstant => "Invalid character constant",
+            ErrorCode::ExpectedExpression => "Expected expression",
+            ErrorCode::ExpectedToken => "Expected token",
+            ErrorCode::UnexpectedOperator => "Unexpected operator",
+            ErrorCode::ExpectedOperator => "Expected operator",
+            ErrorCode::InvalidTypeName => "Invalid type name",
+            ErrorCode::InvalidOperandTypes => "Invalid operand types",
+            ErrorCode::ShiftCountOutOfRange => "Shift count out of range",
+            ErrorCode::NotAssignable => "Expression is not assignable",
+            ErrorCode::UndeclaredIdentifier => "Use of undeclared identifier",
+            ErrorCode::NotConstant => "Expression is not an integer constant",
+            ErrorCode::IncompleteType => "Incomplete type",
+            ErrorCode::NoSuchMember => "No such member",
+            ErrorCode::InvalidType => "Invalid type",
+            ErrorCode::Redeclaration => "Redeclaration",
+            ErrorCode::InvalidStorageClass => "Invalid storage class",
+            ErrorCode::UndefinedReference => "Undefined reference",
+            ErrorCode::NotInLoop => "Statement not within a loop",
+            ErrorCode::StackUnderflow => "Stack underflow",
+            ErrorCode::IntegerOverflow => "Integer overflow",
+            ErrorCode::DivisionByZero => "Division by zero",
+            ErrorCode::InvalidOperands => "Invalid operands",
+            ErrorCode::InvalidShift => "Invalid shift amount",
+            ErrorCode::NarrowingConversion => "Implicit conversion may change value",
+            ErrorCode::Uninitialized => "Variable is used uninitialized",
+            ErrorCode::AssignmentAsCondition => "Assignment used as truth value",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Label {
+    pub message: String,
+    pub info: TokenInfo,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Note {
+    Note(String),
+    Help(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Error {
+    pub code: ErrorCode,
+    pub message: String,
+    pub info: TokenInfo,
+    pub labels: Vec<Label>,
+    pub notes: Vec<Note>,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn new(code: ErrorCode, message: impl Into<String>, info: TokenInfo) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            info,
+            labels: vec![],
+            notes: vec![],
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.code.kind()
+    }
+
+    pub fn with_label(mut self, message: impl Into<String>, info: TokenInfo) -> Self {
+        self.labels.push(Label {
+            message: message.into(),
+            info,
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(Note::Note(note.into()));
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.notes.push(Note::Help(help.into()));
+        self
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Lexical => f.write_str("lexical error"),
+            ErrorKind::Syntax => f.write_str("syntax error"),
+            ErrorKind::Semantic => f.write_str("semantic error"),
+            ErrorKind::Runtime => f.write_str("runtime error"),
+            ErrorKind::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}] at line {}, col {}: {}",
+            self.kind(),
+            self.code,
+            self.info.line + 1,
+            self.info.column + 1,
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for Error {}
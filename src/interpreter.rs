@@ -1,108 +1,308 @@
+use std::str::FromStr;
+
 use crate::{
-    opcode::{Op, OpCode},
+    error::{Error, ErrorCode, Result},
+    opcode::{Chunk, Op, OpCode},
+    token::TokenInfo,
+    types::Type,
     value::Value,
 };
 
+/// What happens when signed integer arithmetic leaves the range of its type.
+///
+/// C leaves signed overflow undefined, so rather than inheriting whatever the
+/// Rust build profile does (panic in debug, wrap in release) the interpreter
+/// always applies one of these explicitly. Division by zero is not overflow:
+/// it is a runtime error in every mode. Unsigned arithmetic is defined to wrap
+/// and always does.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Report a runtime error.
+    #[default]
+    Trap,
+    /// Wrap around in two's complement.
+    Wrap,
+    /// Clamp to the minimum or maximum value of the type.
+    Saturate,
+}
+
+impl Overflow {
+    // Fits the exact result of an integer operation into its type.
+    fn apply(&self, ty: Type, exact: i128) -> std::result::Result<Value, ErrorCode> {
+        let (min, max) = (ty.min_value(), ty.max_value());
+        if ty.is_unsigned() || (min..=max).contains(&exact) {
+            return Ok(Value::from_integer(ty, exact));
+        }
+        match self {
+            Overflow::Trap => Err(ErrorCode::IntegerOverflow),
+            Overflow::Wrap => Ok(Value::from_integer(ty, exact)),
+            Overflow::Saturate => Ok(Value::from_integer(ty, exact.clamp(min, max))),
+        }
+    }
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "trap" => Ok(Overflow::Trap),
+            "wrap" => Ok(Overflow::Wrap),
+            "saturate" => Ok(Overflow::Saturate),
+            _ => Err(format!(
+                "unknown overflow mode '{}', expected one of trap, wrap, saturate",
+                s
+            )),
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct Interpreter {
-    ops: Vec<OpCode>,
+    chunk: Chunk,
     index: usize,
     stack: Vec<Value>,
+    spans: Vec<TokenInfo>,
+    globals: Vec<Value>,
+    overflow: Overflow,
 }
 
 impl Interpreter {
-    pub fn new(ops: &[OpCode]) -> Self {
+    pub fn new(chunk: &Chunk) -> Self {
         Self {
-            ops: ops.to_vec(),
+            chunk: chunk.clone(),
+            globals: chunk.globals.clone(),
             ..Default::default()
         }
     }
 
-    pub fn interpret(&mut self) -> Value {
-        while self.index < self.ops.len() {
-            let op = &self.ops[self.index];
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    pub fn interpret(&mut self) -> Result<Option<Value>> {
+        while self.index < self.chunk.ops.len() {
+            let op = &self.chunk.ops[self.index];
             match op {
-                OpCode::Constant(value) => self.stack.push(value.clone()),
-                OpCode::Return => return self.stack.last().unwrap().clone(),
-                OpCode::Op(_) => self.interpret_bin_op(op.clone()),
-                OpCode::Negate => {
-                    let top = self.stack.pop().unwrap();
-                    self.stack.push(match top {
-                        Value::Float(val) => Value::Float(-val),
-                        Value::Integer(val) => Value::Integer(-val),
-                        _ => panic!("Cannot negate a bool"),
-                    });
+                OpCode::Constant(value) => self.push(value.clone(), self.info()),
+                OpCode::Return => return Ok(self.stack.last().cloned()),
+                OpCode::Op(op) => self.interpret_bin_op(op.clone())?,
+                OpCode::Negate | OpCode::Not | OpCode::BitNot => {
+                    self.interpret_unary_op(op.clone())?
+                }
+                OpCode::JumpIfFalse(offset) => {
+                    let offset = *offset;
+                    if self.pop()?.0.is_falsey() {
+                        self.index += offset;
+                    }
+                }
+                OpCode::Jump(offset) => self.index += offset,
+                OpCode::Loop(offset) => {
+                    self.index = self.index + 1 - offset;
+                    continue;
+                }
+                OpCode::Pop => {
+                    self.pop()?;
+                }
+                OpCode::GetLocal(slot) => {
+                    let value = self.local(*slot)?.clone();
+                    self.push(value, self.info());
+                }
+                OpCode::SetLocal(slot) => {
+                    let slot = *slot;
+                    let (value, info) = self.pop()?;
+                    *self.local(slot)? = value.clone();
+                    self.push(value, info);
+                }
+                OpCode::GetGlobal(index) => {
+                    let value = self.global(*index)?.clone();
+                    self.push(value, self.info());
+                }
+                OpCode::SetGlobal(index) => {
+                    let index = *index;
+                    let (value, info) = self.pop()?;
+                    *self.global(index)? = value.clone();
+                    self.push(value, info);
+                }
+                OpCode::Cast(ty) => {
+                    let ty = *ty;
+                    let (value, info) = self.pop()?;
+                    // Strings have no address to convert to an integer. The parser
+                    // only rejects literals, not strings reached through variables.
+                    if let (Value::String(_), true) = (&value, ty.is_integer() && ty != Type::Bool)
+                    {
+                        return Err(Error::new(
+                            ErrorCode::InvalidOperands,
+                            format!("Cast of a string literal to type '{}' is not supported", ty),
+                            self.info(),
+                        )
+                        .with_label(
+                            format!(
+                                "string literals have no address, and this evaluates to {}",
+                                value
+                            ),
+                            info,
+                        ));
+                    }
+                    self.push(value.cast(ty), info);
                 }
             }
             self.index += 1;
         }
-        self.stack.last().unwrap().clone()
+        Ok(self.stack.last().cloned())
     }
 
-    fn interpret_bin_op(&mut self, op: OpCode) {
-        let b = self.stack.pop().unwrap();
-        let a = self.stack.pop().unwrap();
-
-        match (a, b) {
-            (Value::Integer(a), Value::Integer(b)) => self.stack.push(match op {
-                OpCode::Op(Op::Plus) => Value::from(a + b),
-                OpCode::Op(Op::Minus) => Value::from(a - b),
-                OpCode::Op(Op::Multiply) => Value::from(a * b),
-                OpCode::Op(Op::Divide) => Value::from(a / b),
-                OpCode::Op(Op::EqualEqual) => Value::Bool(a == b),
-                OpCode::Op(Op::BangEqual) => Value::Bool(a != b),
-                OpCode::Op(Op::Greater) => Value::Bool(a > b),
-                OpCode::Op(Op::GreaterEqual) => Value::Bool(a >= b),
-                OpCode::Op(Op::Less) => Value::Bool(a < b),
-                OpCode::Op(Op::LessEqual) => Value::Bool(a <= b),
-                _ => panic!("Invalid binary op"),
-            }),
-            (Value::Float(a), Value::Float(b)) => self.stack.push(match op {
-                OpCode::Op(Op::Plus) => Value::from(a + b),
-                OpCode::Op(Op::Minus) => Value::from(a - b),
-                OpCode::Op(Op::Multiply) => Value::from(a * b),
-                OpCode::Op(Op::Divide) => Value::from(a / b),
-                OpCode::Op(Op::EqualEqual) => Value::Bool(a == b),
-                OpCode::Op(Op::BangEqual) => Value::Bool(a != b),
-                OpCode::Op(Op::Greater) => Value::Bool(a > b),
-                OpCode::Op(Op::GreaterEqual) => Value::Bool(a >= b),
-                OpCode::Op(Op::Less) => Value::Bool(a < b),
-                OpCode::Op(Op::LessEqual) => Value::Bool(a <= b),
-                _ => panic!("Invalid binary op"),
-            }),
-            (Value::Float(a), Value::Integer(b)) => self.stack.push(match op {
-                OpCode::Op(Op::Plus) => Value::from(a + b as f64),
-                OpCode::Op(Op::Minus) => Value::from(a - b as f64),
-                OpCode::Op(Op::Multiply) => Value::from(a * b as f64),
-                OpCode::Op(Op::Divide) => Value::from(a / b as f64),
-                OpCode::Op(Op::EqualEqual) => Value::Bool(a == b as f64),
-                OpCode::Op(Op::BangEqual) => Value::Bool(a != b as f64),
-                OpCode::Op(Op::Greater) => Value::Bool(a > b as f64),
-                OpCode::Op(Op::GreaterEqual) => Value::Bool(a >= b as f64),
-                OpCode::Op(Op::Less) => Value::Bool(a < b as f64),
-                OpCode::Op(Op::LessEqual) => Value::Bool(a <= b as f64),
-                _ => panic!("Invalid binary op"),
-            }),
-            (Value::Integer(a), Value::Float(b)) => self.stack.push(match op {
-                OpCode::Op(Op::Plus) => Value::from(a as f64 + b),
-                OpCode::Op(Op::Minus) => Value::from(a as f64 - b),
-                OpCode::Op(Op::Multiply) => Value::from(a as f64 * b),
-                OpCode::Op(Op::Divide) => Value::from(a as f64 / b),
-                OpCode::Op(Op::EqualEqual) => Value::Bool(a as f64 == b),
-                OpCode::Op(Op::BangEqual) => Value::Bool(a as f64 != b),
-                OpCode::Op(Op::Greater) => Value::Bool(a as f64 > b),
-                OpCode::Op(Op::GreaterEqual) => Value::Bool(a as f64 >= b),
-                OpCode::Op(Op::Less) => Value::Bool((a as f64) < b),
-                OpCode::Op(Op::LessEqual) => Value::Bool(a as f64 <= b),
-                _ => panic!("Invalid binary op"),
-            }),
-            (Value::Bool(a), Value::Bool(b)) => {
-                self.stack.push(match op {
-                    OpCode::Op(Op::EqualEqual) => Value::Bool(a == b),
-                    _ => panic!("Invalid binary op"),
-                });
-            }
-            _ => panic!("Invalid binary op"),
+    fn info(&self) -> TokenInfo {
+        self.chunk.spans[self.index].clone()
+    }
+
+    fn push(&mut self, value: Value, info: TokenInfo) {
+        self.stack.push(value);
+        self.spans.push(info);
+    }
+
+    fn local(&mut self, slot: usize) -> Result<&mut Value> {
+        let info = self.info();
+        self.stack.get_mut(slot).ok_or_else(|| {
+            Error::new(
+                ErrorCode::StackUnderflow,
+                ErrorCode::StackUnderflow.description(),
+                info,
+            )
+        })
+    }
+
+    fn global(&mut self, index: usize) -> Result<&mut Value> {
+        let info = self.info();
+        self.globals.get_mut(index).ok_or_else(|| {
+            Error::new(
+                ErrorCode::UndefinedReference,
+                ErrorCode::UndefinedReference.description(),
+                info,
+            )
+        })
+    }
+
+    fn pop(&mut self) -> Result<(Value, TokenInfo)> {
+        match (self.stack.pop(), self.spans.pop()) {
+            (Some(value), Some(info)) => Ok((value, info)),
+            _ => Err(Error::new(
+                ErrorCode::StackUnderflow,
+                ErrorCode::StackUnderflow.description(),
+                self.info(),
+            )),
+        }
+    }
+
+    fn interpret_unary_op(&mut self, op: OpCode) -> Result<()> {
+        let (a, a_info) = self.pop()?;
+        let info = self.info();
+
+        let value = self.unary_op(&op, a.clone()).map_err(|code| {
+            Error::new(code, code.description(), info.clone())
+                .with_label(format!("operand evaluates to {}", a), a_info.clone())
+        })?;
+        self.push(value, info.merge(&a_info));
+        Ok(())
+    }
+
+    fn unary_op(&self, op: &OpCode, a: Value) -> std::result::Result<Value, ErrorCode> {
+        if *op == OpCode::Not {
+            return Ok(Value::Int(a.is_falsey() as i32));
+        }
+        let ty = a.ty().promote();
+        match (op, a.cast(ty).as_i128(), a.as_f64()) {
+            (OpCode::Negate, Some(a), _) => self.overflow.apply(ty, -a),
+            (OpCode::Negate, None, Some(a)) => Ok(Value::from_floating(ty, -a)),
+            (OpCode::BitNot, Some(a), _) => Ok(Value::from_integer(ty, !a)),
+            _ => Err(ErrorCode::InvalidOperands),
+        }
+    }
+
+    fn interpret_bin_op(&mut self, op: Op) -> Result<()> {
+        let (b, b_info) = self.pop()?;
+        let (a, a_info) = self.pop()?;
+        let info = self.info();
+
+        let value = self.bin_op(&op, a.clone(), b.clone()).map_err(|code| {
+            Error::new(code, code.description(), info)
+                .with_label(format!("left operand evaluates to {}", a), a_info.clone())
+                .with_label(format!("right operand evaluates to {}", b), b_info.clone())
+        })?;
+        self.push(value, a_info.merge(&b_info));
+        Ok(())
+    }
+
+    // Every binary operator goes through the usual arithmetic conversions,
+    // except shifts, whose operands are promoted separately.
+    fn bin_op(&self, op: &Op, a: Value, b: Value) -> std::result::Result<Value, ErrorCode> {
+        if !a.ty().is_arithmetic() || !b.ty().is_arithmetic() {
+            return Err(ErrorCode::InvalidOperands);
+        }
+        if let Op::ShiftLeft | Op::ShiftRight = op {
+            return self.shift(op, a, b);
+        }
+
+        let ty = a.ty().common(b.ty());
+        let (a, b) = (a.cast(ty), b.cast(ty));
+        let compare = |ordering: Option<std::cmp::Ordering>| {
+            use std::cmp::Ordering::*;
+            let result = match op {
+                Op::EqualEqual => ordering == Some(Equal),
+                Op::BangEqual => ordering != Some(Equal),
+                Op::Greater => ordering == Some(Greater),
+                Op::GreaterEqual => matches!(ordering, Some(Greater | Equal)),
+                Op::Less => ordering == Some(Less),
+                _ => matches!(ordering, Some(Less | Equal)),
+            };
+            Value::Int(result as i32)
+        };
+
+        if let (Some(a), Some(b)) = (a.as_i128(), b.as_i128()) {
+            let overflow = self.overflow;
+            return match op {
+                Op::Plus => overflow.apply(ty, a + b),
+                Op::Minus => overflow.apply(ty, a - b),
+                // Only unsigned 64-bit products leave the range of `i128`,
+                // and their low bits are all that is kept.
+                Op::Multiply => overflow.apply(ty, a.wrapping_mul(b)),
+                Op::Divide | Op::Modulo if b == 0 => Err(ErrorCode::DivisionByZero),
+                Op::Divide => overflow.apply(ty, a / b),
+                Op::Modulo => overflow.apply(ty, a % b),
+                Op::BitAnd => Ok(Value::from_integer(ty, a & b)),
+                Op::BitOr => Ok(Value::from_integer(ty, a | b)),
+                Op::BitXor => Ok(Value::from_integer(ty, a ^ b)),
+                _ => Ok(compare(a.partial_cmp(&b))),
+            };
+        }
+
+        let (Some(a), Some(b)) = (a.as_f64(), b.as_f64()) else {
+            return Err(ErrorCode::InvalidOperands);
+        };
+        match op {
+            Op::Plus => Ok(Value::from_floating(ty, a + b)),
+            Op::Minus => Ok(Value::from_floating(ty, a - b)),
+            Op::Multiply => Ok(Value::from_floating(ty, a * b)),
+            Op::Divide => Ok(Value::from_floating(ty, a / b)),
+            Op::Modulo | Op::BitAnd | Op::BitOr | Op::BitXor => Err(ErrorCode::InvalidOperands),
+            _ => Ok(compare(a.partial_cmp(&b))),
+        }
+    }
+
+    fn shift(&self, op: &Op, a: Value, b: Value) -> std::result::Result<Value, ErrorCode> {
+        let ty = a.ty().promote();
+        let (Some(a), Some(b)) = (a.cast(ty).as_i128(), b.as_i128()) else {
+            return Err(ErrorCode::InvalidOperands);
+        };
+        if !(0..ty.bits() as i128).contains(&b) {
+            return Err(ErrorCode::InvalidShift);
+        }
+        match op {
+            // Shifting a negative value left is undefined in C and is
+            // treated like any other signed overflow.
+            Op::ShiftLeft => self.overflow.apply(ty, a << b),
+            _ => Ok(Value::from_integer(ty, a >> b)),
         }
     }
 }
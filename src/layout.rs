@@ -0,0 +1,179 @@
+use std::fmt;
+
+use crate::types::Type;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordKind {
+    Struct,
+    Union,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub name: String,
+    pub ty: CType,
+}
+
+// A struct or union. Its members are unknown until it is defined, and until
+// then it is an incomplete type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub kind: RecordKind,
+    pub tag: Option<String>,
+    pub members: Option<Vec<Member>>,
+}
+
+// Object types, laid out as gcc does under the x86-64 System V ABI. Only
+// scalars have values; the others exist for `sizeof` and `offsetof`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CType {
+    Void,
+    Scalar(Type),
+    Array(Box<CType>, usize),
+    Record(Record),
+}
+
+impl CType {
+    // `None` for incomplete types, which have no size.
+    pub fn size(&self) -> Option<usize> {
+        match self {
+            CType::Void => None,
+            CType::Scalar(ty) => Some(ty.size()),
+            CType::Array(element, len) => Some(element.size()? * len),
+            CType::Record(record) => {
+                let members = record.members.as_ref()?;
+                let end = match record.kind {
+                    RecordKind::Struct => members.last().map_or(0, |last| {
+                        self.offset_of(&last.name).unwrap() + last.ty.size().unwrap()
+                    }),
+                    RecordKind::Union => members
+                        .iter()
+                        .map(|member| member.ty.size().unwrap())
+                        .max()
+                        .unwrap_or(0),
+                };
+                Some(align_to(end, self.align()?))
+            }
+        }
+    }
+
+    // Every scalar is aligned to its size, `long double` included.
+    pub fn align(&self) -> Option<usize> {
+        match self {
+            CType::Void => None,
+            CType::Scalar(ty) => Some(ty.size()),
+            CType::Array(element, _) => element.align(),
+            CType::Record(record) => record
+                .members
+                .as_ref()?
+                .iter()
+                .map(|member| member.ty.align())
+                .try_fold(1, |align, member| Some(align.max(member?))),
+        }
+    }
+
+    // Each struct member starts at the next offset aligned for its type,
+    // while every union member starts at 0.
+    pub fn offset_of(&self, name: &str) -> Option<usize> {
+        let CType::Record(record) = self else {
+            return None;
+        };
+        let members = record.members.as_ref()?;
+        if record.kind == RecordKind::Union {
+            return members
+                .iter()
+                .any(|member| member.name == name)
+                .then_some(0);
+        }
+        let mut offset = 0;
+        for member in members {
+            offset = align_to(offset, member.ty.align()?);
+            if member.name == name {
+                return Some(offset);
+            }
+            offset += member.ty.size()?;
+        }
+        None
+    }
+}
+
+fn align_to(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+impl fmt::Display for RecordKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordKind::Struct => f.write_str("struct"),
+            RecordKind::Union => f.write_str("union"),
+        }
+    }
+}
+
+impl fmt::Display for CType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CType::Void => f.write_str("void"),
+            CType::Scalar(ty) => ty.fmt(f),
+            CType::Array(..) => {
+                // The outermost bound is written first, as in `int[2][3]`.
+                let (mut element, mut bounds) = (self, String::new());
+                while let CType::Array(inner, len) = element {
+                    bounds.push_str(&format!("[{}]", len));
+                    element = inner;
+                }
+                write!(f, "{}{}", element, bounds)
+            }
+            CType::Record(record) => match &record.tag {
+                Some(tag) => write!(f, "{} {}", record.kind, tag),
+                None => write!(f, "{} <anonymous>", record.kind),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(kind: RecordKind, members: &[(&str, CType)]) -> CType {
+        CType::Record(Record {
+            kind,
+            tag: None,
+            members: Some(
+                members
+                    .iter()
+                    .map(|(name, ty)| Member {
+                        name: name.to_string(),
+                        ty: ty.clone(),
+                    })
+                    .collect(),
+            ),
+        })
+    }
+
+    #[test]
+    fn test_struct_layout() {
+        let char = CType::Scalar(Type::Char);
+        let long_double = CType::Scalar(Type::LongDouble);
+        let s = record(
+            RecordKind::Struct,
+            &[
+                ("a", char.clone()),
+                ("b", CType::Scalar(Type::Int)),
+                ("c", CType::Array(Box::new(char.clone()), 3)),
+                ("d", CType::Scalar(Type::Pointer)),
+            ],
+        );
+        assert_eq!(s.offset_of("b"), Some(4));
+        assert_eq!(s.offset_of("c"), Some(8));
+        assert_eq!(s.offset_of("d"), Some(16));
+        assert_eq!(s.size(), Some(24));
+        assert_eq!(s.align(), Some(8));
+
+        let u = record(RecordKind::Union, &[("a", char), ("b", long_double)]);
+        assert_eq!(u.offset_of("b"), Some(0));
+        assert_eq!(u.size(), Some(16));
+        assert_eq!(record(RecordKind::Struct, &[]).size(), Some(0));
+    }
+}
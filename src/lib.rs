@@ -1,7 +1,12 @@
 pub mod compiler;
+pub mod diagnostic;
+pub mod error;
 pub mod interpreter;
+pub mod layout;
 pub mod opcode;
+pub mod optimizer;
 pub mod parser;
 pub mod scanner;
 pub mod token;
+pub mod types;
 pub mod value;
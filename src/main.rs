@@ -1,70 +1,191 @@
 use std::{
     fs,
     io::{self, IsTerminal, Write},
+    process,
 };
 
-use mini_c::{interpreter::Interpreter, parser::Parser, scanner::Scanner};
+use mini_c::{
+    diagnostic::Emitter,
+    error::Error,
+    interpreter::{Interpreter, Overflow},
+    optimizer,
+    parser::{Parser, Warning},
+    scanner::Scanner,
+    value::Value,
+};
 
 use std::env;
 
-fn piped() {
-    let stdin = io::stdin().lock();
+#[derive(Default)]
+enum ErrorFormat {
+    #[default]
+    Human,
+    Json,
+}
 
-    let input = io::read_to_string(stdin).unwrap();
+#[derive(Default)]
+struct Options {
+    path: Option<String>,
+    overflow: Overflow,
+    error_format: ErrorFormat,
+    warnings: Vec<Warning>,
+    optimize: bool,
+}
 
-    let mut scanner = Scanner::new(input);
-    let tokens = scanner.scan();
-    let mut parser = Parser::new(&tokens);
-    let ops = parser.parse();
-    let mut interpreter = Interpreter::new(&ops);
-    let result = interpreter.interpret();
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options::default();
+        for arg in args {
+            if let Some(mode) = arg.strip_prefix("--overflow=") {
+                options.overflow = mode.parse()?;
+            } else if let Some(warnings) = arg.strip_prefix("--warn=") {
+                for warning in warnings.split(',') {
+                    options.warnings.push(warning.parse()?);
+                }
+            } else if let Some(optimize) = arg.strip_prefix("--optimize=") {
+                options.optimize = match optimize {
+                    "on" => true,
+                    "off" => false,
+                    _ => {
+                        return Err(format!(
+                            "unknown optimize setting '{}', expected one of on, off",
+                            optimize
+                        ))
+                    }
+                };
+            } else if let Some(format) = arg.strip_prefix("--error-format=") {
+                options.error_format = match format {
+                    "human" => ErrorFormat::Human,
+                    "json" => ErrorFormat::Json,
+                    _ => {
+                        return Err(format!(
+                            "unknown error format '{}', expected one of human, json",
+                            format
+                        ))
+                    }
+                };
+            } else if arg.starts_with("--") {
+                return Err(format!("unknown option '{}'", arg));
+            } else if options.path.is_none() {
+                options.path = Some(arg.clone());
+            } else {
+                return Err(format!("unexpected argument '{}'", arg));
+            }
+        }
+        Ok(options)
+    }
+}
 
-    println!("{}", result);
+fn run(
+    input: &str,
+    options: &Options,
+    warnings: &mut Vec<Error>,
+) -> Result<Option<Value>, Vec<Error>> {
+    let mut scanner = Scanner::new(input.to_string());
+    let tokens = scanner.scan()?;
+    let mut parser = options.warnings.iter().fold(
+        Parser::new(&tokens).with_overflow(options.overflow),
+        |parser, &warning| parser.with_warning(warning),
+    );
+    let result = parser.parse();
+    warnings.extend_from_slice(parser.warnings());
+    let mut chunk = result?;
+    if options.optimize {
+        chunk = optimizer::optimize(&chunk);
+    }
+    let mut interpreter = Interpreter::new(&chunk).with_overflow(options.overflow);
+    interpreter.interpret().map_err(|err| vec![err])
 }
 
-fn repl() -> Result<(), io::Error> {
+fn report(input: &str, path: &str, options: &Options) -> bool {
+    let mut warnings = vec![];
+    let result = run(input, options, &mut warnings);
+    let errors = result.as_ref().err().cloned().unwrap_or_default();
+
+    let emitter = Emitter::new(input, path);
+    match options.error_format {
+        ErrorFormat::Human => {
+            for (i, err) in warnings.iter().chain(&errors).enumerate() {
+                if i > 0 {
+                    eprintln!();
+                }
+                eprint!("{}", emitter.render(err));
+            }
+            if errors.len() > 1 {
+                eprintln!("\naborting due to {} previous errors", errors.len());
+            }
+        }
+        ErrorFormat::Json => {
+            for err in warnings.iter().chain(&errors) {
+                eprintln!("{}", emitter.render_json(err));
+            }
+        }
+    }
+
+    match result {
+        Ok(Some(value)) => {
+            println!("{}", value);
+            true
+        }
+        Ok(None) => true,
+        Err(_) => false,
+    }
+}
+
+fn piped(options: &Options) -> Result<bool, io::Error> {
+    let stdin = io::stdin().lock();
+
+    let input = io::read_to_string(stdin)?;
+
+    Ok(report(&input, "<stdin>", options))
+}
+
+fn repl(options: &Options) -> Result<(), io::Error> {
     loop {
         let mut buffer = String::new();
         print!("> ");
         io::stdout().flush()?;
-        io::stdin().read_line(&mut buffer)?;
-        let mut scanner = Scanner::new(buffer);
-        let tokens = scanner.scan();
-        let mut parser = Parser::new(&tokens);
-        let ops = parser.parse();
-        let mut interpreter = Interpreter::new(&ops);
-        let result = interpreter.interpret();
-
-        println!("{}", result);
+        if io::stdin().read_line(&mut buffer)? == 0 {
+            return Ok(());
+        }
+        report(&buffer, "<stdin>", options);
     }
 }
 
-fn file(args: &[String]) -> Result<(), io::Error> {
-    let input = fs::read_to_string(&args[1]).unwrap();
-
-    let mut scanner = Scanner::new(input);
-    let tokens = scanner.scan();
-    let mut parser = Parser::new(&tokens);
-    let ops = parser.parse();
-    let mut interpreter = Interpreter::new(&ops);
-    let result = interpreter.interpret();
+fn file(path: &str, options: &Options) -> Result<bool, io::Error> {
+    let input = fs::read_to_string(path)?;
 
-    println!("{}", result);
-    Ok(())
+    Ok(report(&input, path, options))
 }
 
 fn main() {
-    let args: Vec<_> = env::args().collect();
+    let args: Vec<_> = env::args().skip(1).collect();
 
-    match args.len() {
-        1 => {
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(2);
+        }
+    };
+
+    let result = match &options.path {
+        Some(path) => file(path, &options),
+        None => {
             if std::io::stdin().is_terminal() {
-                repl().unwrap();
+                repl(&options).map(|_| true)
             } else {
-                piped();
+                piped(&options)
             }
         }
-        2 => file(&args).unwrap(),
-        _ => {}
+    };
+
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
     }
 }
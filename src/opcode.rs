@@ -1,6 +1,6 @@
 use std::fmt;
 
-use crate::value::Value;
+use crate::{token::TokenInfo, types::Type, value::Value};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Op {
@@ -8,6 +8,12 @@ pub enum Op {
     Minus,
     Multiply,
     Divide,
+    Modulo,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
     EqualEqual,
     BangEqual,
     GreaterEqual,
@@ -22,30 +28,85 @@ pub enum OpCode {
     Op(Op),
     Return,
     Negate,
+    Not,
+    BitNot,
+    // Jump offsets count ops forward from the one after the jump.
+    // `JumpIfFalse` pops the condition it tests.
+    JumpIfFalse(usize),
+    Jump(usize),
+    // Jumps back by its offset, from the op after it, to repeat a loop.
+    Loop(usize),
+    Cast(Type),
+    Pop,
+    // Local variables live on the stack, each in the slot its declaration
+    // pushed. `SetLocal` leaves the stored value on the stack, as an
+    // assignment expression evaluates to it.
+    GetLocal(usize),
+    SetLocal(usize),
+    // Variables that live as long as the program are kept apart from the
+    // stack, and indexed the same way.
+    GetGlobal(usize),
+    SetGlobal(usize),
+}
+
+// The source span of each op lives at the same index in `spans`, so runtime
+// errors can point back at the expression that produced them.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub ops: Vec<OpCode>,
+    pub spans: Vec<TokenInfo>,
+    // The value each global starts out with.
+    pub globals: Vec<Value>,
+}
+
+impl Chunk {
+    pub fn write(&mut self, op: OpCode, info: TokenInfo) {
+        self.ops.push(op);
+        self.spans.push(info);
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::Plus => f.write_str("+"),
+            Op::Minus => f.write_str("-"),
+            Op::Multiply => f.write_str("*"),
+            Op::Divide => f.write_str("/"),
+            Op::Modulo => f.write_str("%"),
+            Op::BitAnd => f.write_str("&"),
+            Op::BitOr => f.write_str("|"),
+            Op::BitXor => f.write_str("^"),
+            Op::ShiftLeft => f.write_str("<<"),
+            Op::ShiftRight => f.write_str(">>"),
+            Op::EqualEqual => f.write_str("=="),
+            Op::BangEqual => f.write_str("!="),
+            Op::GreaterEqual => f.write_str(">="),
+            Op::Greater => f.write_str(">"),
+            Op::Less => f.write_str("<"),
+            Op::LessEqual => f.write_str("<="),
+        }
+    }
 }
 
 impl fmt::Display for OpCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            OpCode::Constant(constant) => match constant {
-                Value::Float(num) => f.write_str(&num.to_string()),
-                Value::Integer(num) => f.write_str(&num.to_string()),
-                Value::Bool(b) => f.write_str(&b.to_string()),
-            },
-            OpCode::Op(op) => match op {
-                Op::Plus => f.write_str("+"),
-                Op::Minus => f.write_str("-"),
-                Op::Multiply => f.write_str("*"),
-                Op::Divide => f.write_str("/"),
-                Op::EqualEqual => f.write_str("=="),
-                Op::BangEqual => f.write_str("!="),
-                Op::GreaterEqual => f.write_str(">="),
-                Op::Greater => f.write_str(">"),
-                Op::Less => f.write_str("<"),
-                Op::LessEqual => f.write_str("<="),
-            },
+            OpCode::Constant(constant) => constant.fmt(f),
+            OpCode::Op(op) => op.fmt(f),
             OpCode::Return => f.write_str("return"),
             OpCode::Negate => f.write_str("-"),
+            OpCode::Not => f.write_str("!"),
+            OpCode::BitNot => f.write_str("~"),
+            OpCode::JumpIfFalse(offset) => write!(f, "jump_if_false +{}", offset),
+            OpCode::Jump(offset) => write!(f, "jump +{}", offset),
+            OpCode::Loop(offset) => write!(f, "loop -{}", offset),
+            OpCode::Cast(ty) => write!(f, "({})", ty),
+            OpCode::Pop => f.write_str("pop"),
+            OpCode::GetLocal(slot) => write!(f, "get_local {}", slot),
+            OpCode::SetLocal(slot) => write!(f, "set_local {}", slot),
+            OpCode::GetGlobal(index) => write!(f, "get_global {}", index),
+            OpCode::SetGlobal(index) => write!(f, "set_global {}", index),
         }
     }
 }
@@ -0,0 +1,364 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    interpreter::Interpreter,
+    opcode::{Chunk, Op, OpCode},
+    token::TokenInfo,
+    types::Type,
+    value::Value,
+};
+
+// Folds constant subexpressions and drops identity operations in one pass
+// over the chunk, without changing what it evaluates to or which error it
+// reports. An operation is folded by running it, with overflow trapping, and
+// anything that fails is left for runtime. Folding never crosses a jump
+// target, and jump offsets are remapped once the ops have moved. A loop is
+// only entered from the code before it, so one that can't be reached that
+// way is dropped whole.
+pub fn optimize(chunk: &Chunk) -> Chunk {
+    let mut optimizer = Optimizer::default();
+    optimizer.out.globals = chunk.globals.clone();
+    optimizer.run(chunk)
+}
+
+#[derive(Default)]
+struct Optimizer {
+    out: Chunk,
+    // The static type of each value on the stack, where it is known.
+    stack: Vec<Option<Type>>,
+    // Ops before this index may be jumped over, so they are never folded.
+    barrier: usize,
+    // Each emitted jump with the index of its target in the input chunk.
+    jumps: Vec<(usize, usize)>,
+    // The stack at each jump target, as seen by the jumps to it.
+    targets: HashMap<usize, Vec<Option<Type>>>,
+    reachable: bool,
+}
+
+impl Optimizer {
+    fn run(mut self, chunk: &Chunk) -> Chunk {
+        let targets: HashSet<_> = (0..chunk.ops.len())
+            .filter_map(|i| jump_target(&chunk.ops[i], i))
+            .collect();
+        let starts: HashSet<_> = (0..chunk.ops.len())
+            .filter_map(|i| match chunk.ops[i] {
+                OpCode::Loop(offset) => Some(i + 1 - offset),
+                _ => None,
+            })
+            .collect();
+        let mut map = Vec::with_capacity(chunk.ops.len() + 1);
+        self.reachable = true;
+
+        for (i, (op, info)) in chunk.ops.iter().zip(&chunk.spans).enumerate() {
+            if targets.contains(&i) {
+                self.land(i);
+            }
+            // A loop jumps back to its start, so nothing folds across it.
+            if starts.contains(&i) && self.reachable {
+                self.barrier = self.out.ops.len();
+            }
+            map.push(self.out.ops.len());
+            // Code that nothing jumps to after an unconditional jump is dead.
+            if self.reachable {
+                self.op(i, op.clone(), info.clone());
+            }
+        }
+        self.land(chunk.ops.len());
+        map.push(self.out.ops.len());
+
+        for (jump, target) in self.jumps {
+            match &mut self.out.ops[jump] {
+                OpCode::Jump(offset) | OpCode::JumpIfFalse(offset) => {
+                    *offset = map[target] - jump - 1
+                }
+                OpCode::Loop(offset) => *offset = jump + 1 - map[target],
+                op => unreachable!("cannot patch non-jump op '{}'", op),
+            }
+        }
+        self.out
+    }
+
+    fn land(&mut self, target: usize) {
+        if !self.reachable {
+            let Some(stack) = self.targets.get(&target) else {
+                return;
+            };
+            self.stack = stack.clone();
+            self.reachable = true;
+            // A jump straight to the next live op does nothing.
+            if let Some(&(jump, to)) = self.jumps.last() {
+                if jump + 1 == self.out.ops.len() && to == target {
+                    self.jumps.pop();
+                    self.pop_op();
+                }
+            }
+        }
+        // Jumps that were folded away no longer split the code here.
+        if self.jumps.iter().any(|&(_, to)| to == target) {
+            self.barrier = self.out.ops.len();
+        }
+    }
+
+    fn op(&mut self, index: usize, op: OpCode, info: TokenInfo) {
+        match op {
+            OpCode::Constant(ref value) => {
+                self.stack.push(Some(value.ty()));
+                self.emit(op, info);
+            }
+            // Locals live on the stack, so their types are found there too.
+            OpCode::GetLocal(slot) => {
+                self.stack.push(self.stack.get(slot).copied().flatten());
+                self.emit(op, info);
+            }
+            OpCode::SetLocal(slot) => {
+                if let Some(&ty) = self.stack.last() {
+                    self.stack[slot] = ty;
+                }
+                self.emit(op, info);
+            }
+            OpCode::GetGlobal(index) => {
+                self.stack.push(self.out.globals.get(index).map(Value::ty));
+                self.emit(op, info);
+            }
+            OpCode::SetGlobal(_) => self.emit(op, info),
+            OpCode::Pop => {
+                self.stack.pop();
+                let slot = self.stack.len().checked_sub(1);
+                match self.tail(3) {
+                    // A variable initialized with a constant starts out as it.
+                    Some([OpCode::Constant(_), OpCode::Constant(_), OpCode::SetLocal(to)])
+                        if Some(*to) == slot =>
+                    {
+                        self.pop_op();
+                        let (value, span) = self.pop_op();
+                        self.pop_op();
+                        self.emit(value, span);
+                    }
+                    _ => match self.last_op() {
+                        Some(OpCode::Constant(_) | OpCode::GetLocal(_) | OpCode::GetGlobal(_)) => {
+                            self.pop_op();
+                        }
+                        _ => self.emit(op, info),
+                    },
+                }
+            }
+            OpCode::Cast(ty) => {
+                let from = self.stack.pop().flatten();
+                self.stack.push(Some(ty));
+                // A string has no address, so its cast is left to fail when run.
+                let addressless = |value: &Value| {
+                    matches!(value, Value::String(_)) && ty.is_integer() && ty != Type::Bool
+                };
+                if let Some(value) = self.constant(0).filter(|value| !addressless(value)) {
+                    let (_, span) = self.pop_op();
+                    self.emit(OpCode::Constant(value.cast(ty)), span);
+                } else if from != Some(ty) {
+                    self.emit(op, info);
+                }
+            }
+            OpCode::Negate | OpCode::Not | OpCode::BitNot => self.unary(op, info),
+            OpCode::Op(ref bin) => self.binary(bin.clone(), op, info),
+            OpCode::JumpIfFalse(offset) => {
+                self.stack.pop();
+                let target = index + offset + 1;
+                match self.constant(0) {
+                    Some(condition) => {
+                        self.pop_op();
+                        if condition.is_falsey() {
+                            self.jump(OpCode::Jump(0), target, info);
+                            self.reachable = false;
+                        }
+                    }
+                    None => self.jump(OpCode::JumpIfFalse(0), target, info),
+                }
+            }
+            OpCode::Jump(offset) => {
+                self.jump(OpCode::Jump(0), index + offset + 1, info);
+                self.reachable = false;
+            }
+            OpCode::Loop(offset) => {
+                self.jumps.push((self.out.ops.len(), index + 1 - offset));
+                self.emit(op, info);
+                self.reachable = false;
+            }
+            OpCode::Return => self.emit(op, info),
+        }
+    }
+
+    fn unary(&mut self, op: OpCode, info: TokenInfo) {
+        let operand = self.stack.pop().flatten();
+        self.stack.push(match op {
+            OpCode::Not => Some(Type::Int),
+            _ => operand.map(Type::promote),
+        });
+        if self.constant(0).is_some() {
+            let (constant, span) = self.pop_op();
+            if let Some(value) = evaluate(vec![constant.clone(), op.clone()]) {
+                return self.emit(OpCode::Constant(value), info.merge(&span));
+            }
+            self.emit(constant, span);
+        }
+        // Negating twice is exact for floating values, but may overflow for
+        // integers.
+        let last = self.last_op();
+        if op == OpCode::Negate
+            && last == Some(&OpCode::Negate)
+            && operand.is_some_and(|ty| ty.is_floating())
+        {
+            self.pop_op();
+            return;
+        }
+        self.emit(op, info);
+    }
+
+    fn binary(&mut self, bin: Op, op: OpCode, info: TokenInfo) {
+        let right = self.stack.pop().flatten();
+        let left = self.stack.pop().flatten();
+        self.stack.push(match (&bin, left, right) {
+            (Op::ShiftLeft | Op::ShiftRight, Some(left), _) => Some(left.promote()),
+            (Op::ShiftLeft | Op::ShiftRight, None, _) => None,
+            (Op::Plus | Op::Minus | Op::Multiply | Op::Divide, Some(a), Some(b))
+            | (Op::Modulo | Op::BitAnd | Op::BitOr | Op::BitXor, Some(a), Some(b)) => {
+                Some(a.common(b))
+            }
+            (Op::Plus | Op::Minus | Op::Multiply | Op::Divide, _, _)
+            | (Op::Modulo | Op::BitAnd | Op::BitOr | Op::BitXor, _, _) => None,
+            _ => Some(Type::Int),
+        });
+
+        if let (Some(_), Some(_)) = (self.constant(1), self.constant(0)) {
+            let (b, b_span) = self.pop_op();
+            let (a, a_span) = self.pop_op();
+            let span = a_span.merge(&b_span);
+            if let Some(value) = evaluate(vec![a.clone(), b.clone(), op.clone()]) {
+                return self.emit(OpCode::Constant(value), span);
+            }
+            self.emit(a, a_span);
+            self.emit(b, b_span);
+        }
+
+        // `x + 0`, `x - 0`, `x * 1` and `x / 1` leave an arithmetic `x`
+        // unchanged but for the usual arithmetic conversions, except that
+        // adding 0 turns a floating -0.0 into 0.0.
+        let identity = match (&bin, self.constant(0), left) {
+            (Op::Plus, Some(Value::Int(0)), Some(ty)) => ty.is_integer(),
+            (Op::Minus, Some(Value::Int(0)), Some(ty))
+            | (Op::Multiply | Op::Divide, Some(Value::Int(1)), Some(ty)) => ty.is_arithmetic(),
+            _ => false,
+        };
+        if let (true, Some(ty)) = (identity, left) {
+            self.pop_op();
+            let common = ty.common(Type::Int);
+            if common != ty {
+                self.emit(OpCode::Cast(common), info);
+            }
+            return;
+        }
+        self.emit(op, info);
+    }
+
+    // The constant `depth` ops from the end, if it can be folded.
+    fn constant(&self, depth: usize) -> Option<Value> {
+        let index = self.out.ops.len().checked_sub(depth + 1)?;
+        if index < self.barrier {
+            return None;
+        }
+        match &self.out.ops[index] {
+            OpCode::Constant(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    fn last_op(&self) -> Option<&OpCode> {
+        self.tail(1).map(|ops| &ops[0])
+    }
+
+    // The last `len` ops, if none of them may be jumped over.
+    fn tail(&self, len: usize) -> Option<&[OpCode]> {
+        let start = self.out.ops.len().checked_sub(len)?;
+        (start >= self.barrier).then(|| &self.out.ops[start..])
+    }
+
+    fn jump(&mut self, op: OpCode, target: usize, info: TokenInfo) {
+        self.targets
+            .entry(target)
+            .or_insert_with(|| self.stack.clone());
+        self.jumps.push((self.out.ops.len(), target));
+        self.emit(op, info);
+    }
+
+    fn emit(&mut self, op: OpCode, info: TokenInfo) {
+        self.out.write(op, info);
+    }
+
+    fn pop_op(&mut self) -> (OpCode, TokenInfo) {
+        let op = self.out.ops.pop().unwrap();
+        let info = self.out.spans.pop().unwrap();
+        (op, info)
+    }
+}
+
+fn jump_target(op: &OpCode, index: usize) -> Option<usize> {
+    match op {
+        OpCode::Jump(offset) | OpCode::JumpIfFalse(offset) => Some(index + offset + 1),
+        _ => None,
+    }
+}
+
+// Runs a few ops with overflow trapping. Only results that don't depend on
+// the overflow mode are returned.
+fn evaluate(mut ops: Vec<OpCode>) -> Option<Value> {
+    ops.push(OpCode::Return);
+    let spans = vec![TokenInfo::default(); ops.len()];
+    Interpreter::new(&Chunk {
+        ops,
+        spans,
+        ..Default::default()
+    })
+    .interpret()
+    .ok()
+    .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+    use insta::assert_yaml_snapshot;
+
+    fn ops(source: &str) -> Vec<String> {
+        let tokens = Scanner::new(source.to_string()).scan().unwrap();
+        let chunk = Parser::new(&tokens).parse().unwrap();
+        optimize(&chunk)
+            .ops
+            .iter()
+            .map(|op| op.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_folds_constants() {
+        assert_yaml_snapshot!(ops("1 + 2 * 3 - (double)1 / 4"));
+        assert_yaml_snapshot!(ops("1 && 2 ? 3 : 4"));
+        assert_yaml_snapshot!(ops("int x; if (1) x = 1; else x = 2;"));
+    }
+
+    #[test]
+    fn test_loops() {
+        assert_yaml_snapshot!(ops("int i; for (i = 0; i < 1 + 2; i++) i * 1;"));
+        assert_yaml_snapshot!(ops("int i; while (0) i++; do i++; while (0);"));
+    }
+
+    #[test]
+    fn test_keeps_errors() {
+        assert_yaml_snapshot!(ops("1 + 1 / 0"));
+        assert_yaml_snapshot!(ops("2147483647 + 1"));
+    }
+
+    #[test]
+    fn test_identities() {
+        assert_yaml_snapshot!(ops("int x = 1; x * 1 + 0"));
+        assert_yaml_snapshot!(ops("double d = 1.5; d + 0, - -d"));
+        assert_yaml_snapshot!(ops("char c = 1; c - 0"));
+    }
+}
@@ -1,10 +1,14 @@
 // find a way to go through the token stream and reinterpret it properly
 
-use std::{collections::HashMap, ops::Add};
+use std::{collections::HashMap, ops::Add, str::FromStr};
 
 use crate::{
-    opcode::{Op, OpCode},
-    token::{Token, TokenType},
+    error::{Error, ErrorCode, Result},
+    interpreter::{Interpreter, Overflow},
+    layout::{CType, Member, Record, RecordKind},
+    opcode::{Chunk, Op, OpCode},
+    token::{Token, TokenInfo, TokenType},
+    types::Type,
     value::Value,
 };
 
@@ -12,11 +16,17 @@ use crate::{
 pub enum Precedence {
     #[default]
     None,
+    Comma,
     Assignment,
+    Conditional,
     Or,
     And,
+    BitOr,
+    BitXor,
+    BitAnd,
     Equality,
     Comparison,
+    Shift,
     Term,
     Factor,
     Unary,
@@ -29,17 +39,23 @@ impl From<u8> for Precedence {
     fn from(value: u8) -> Self {
         match value {
             0 => Self::None,
-            1 => Self::Assignment,
-            2 => Self::Or,
-            3 => Self::And,
-            4 => Self::Equality,
-            5 => Self::Comparison,
-            6 => Self::Term,
-            7 => Self::Factor,
-            8 => Self::Unary,
-            9 => Self::Call,
-            10 => Self::Primary,
-            11 => Self::Top,
+            1 => Self::Comma,
+            2 => Self::Assignment,
+            3 => Self::Conditional,
+            4 => Self::Or,
+            5 => Self::And,
+            6 => Self::BitOr,
+            7 => Self::BitXor,
+            8 => Self::BitAnd,
+            9 => Self::Equality,
+            10 => Self::Comparison,
+            11 => Self::Shift,
+            12 => Self::Term,
+            13 => Self::Factor,
+            14 => Self::Unary,
+            15 => Self::Call,
+            16 => Self::Primary,
+            17 => Self::Top,
             _ => Self::None,
         }
     }
@@ -57,17 +73,23 @@ impl From<Precedence> for u8 {
     fn from(val: Precedence) -> Self {
         match val {
             Precedence::None => 0,
-            Precedence::Assignment => 1,
-            Precedence::Or => 2,
-            Precedence::And => 3,
-            Precedence::Equality => 4,
-            Precedence::Comparison => 5,
-            Precedence::Term => 6,
-            Precedence::Factor => 7,
-            Precedence::Unary => 8,
-            Precedence::Call => 9,
-            Precedence::Primary => 10,
-            Precedence::Top => 11,
+            Precedence::Comma => 1,
+            Precedence::Assignment => 2,
+            Precedence::Conditional => 3,
+            Precedence::Or => 4,
+            Precedence::And => 5,
+            Precedence::BitOr => 6,
+            Precedence::BitXor => 7,
+            Precedence::BitAnd => 8,
+            Precedence::Equality => 9,
+            Precedence::Comparison => 10,
+            Precedence::Shift => 11,
+            Precedence::Term => 12,
+            Precedence::Factor => 13,
+            Precedence::Unary => 14,
+            Precedence::Call => 15,
+            Precedence::Primary => 16,
+            Precedence::Top => 17,
         }
     }
 }
@@ -83,6 +105,8 @@ pub enum PrefixRule {
     Literal,
     String,
     Variable,
+    Sizeof,
+    Offsetof,
 }
 
 #[non_exhaustive]
@@ -91,6 +115,12 @@ pub enum InfixRule {
     #[default]
     None,
     Binary,
+    And,
+    Or,
+    Conditional,
+    Assign,
+    Postfix,
+    Comma,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
@@ -112,6 +142,20 @@ lazy_static! {
                 precedence: Precedence::Term,
             },
         ),
+        (
+            TokenType::Bang,
+            ParseRule {
+                prefix: PrefixRule::Unary,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::Tilde,
+            ParseRule {
+                prefix: PrefixRule::Unary,
+                ..Default::default()
+            },
+        ),
         (
             TokenType::Integer,
             ParseRule {
@@ -126,6 +170,34 @@ lazy_static! {
                 ..Default::default()
             },
         ),
+        (
+            TokenType::String,
+            ParseRule {
+                prefix: PrefixRule::String,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::True,
+            ParseRule {
+                prefix: PrefixRule::Literal,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::False,
+            ParseRule {
+                prefix: PrefixRule::Literal,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::Null,
+            ParseRule {
+                prefix: PrefixRule::Literal,
+                ..Default::default()
+            },
+        ),
         (
             TokenType::Plus,
             ParseRule {
@@ -150,6 +222,211 @@ lazy_static! {
                 ..Default::default()
             },
         ),
+        (
+            TokenType::Percent,
+            ParseRule {
+                infix: InfixRule::Binary,
+                precedence: Precedence::Factor,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::Ampersand,
+            ParseRule {
+                infix: InfixRule::Binary,
+                precedence: Precedence::BitAnd,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::Pipe,
+            ParseRule {
+                infix: InfixRule::Binary,
+                precedence: Precedence::BitOr,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::Caret,
+            ParseRule {
+                infix: InfixRule::Binary,
+                precedence: Precedence::BitXor,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::LessLess,
+            ParseRule {
+                infix: InfixRule::Binary,
+                precedence: Precedence::Shift,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::GreaterGreater,
+            ParseRule {
+                infix: InfixRule::Binary,
+                precedence: Precedence::Shift,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::AmpersandAmpersand,
+            ParseRule {
+                infix: InfixRule::And,
+                precedence: Precedence::And,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::PipePipe,
+            ParseRule {
+                infix: InfixRule::Or,
+                precedence: Precedence::Or,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::Question,
+            ParseRule {
+                infix: InfixRule::Conditional,
+                precedence: Precedence::Conditional,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::KwSizeof,
+            ParseRule {
+                prefix: PrefixRule::Sizeof,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::KwOffsetof,
+            ParseRule {
+                prefix: PrefixRule::Offsetof,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::Identifier,
+            ParseRule {
+                prefix: PrefixRule::Variable,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::Equal,
+            ParseRule {
+                infix: InfixRule::Assign,
+                precedence: Precedence::Assignment,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::PlusEqual,
+            ParseRule {
+                infix: InfixRule::Assign,
+                precedence: Precedence::Assignment,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::MinusEqual,
+            ParseRule {
+                infix: InfixRule::Assign,
+                precedence: Precedence::Assignment,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::StarEqual,
+            ParseRule {
+                infix: InfixRule::Assign,
+                precedence: Precedence::Assignment,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::SlashEqual,
+            ParseRule {
+                infix: InfixRule::Assign,
+                precedence: Precedence::Assignment,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::PercentEqual,
+            ParseRule {
+                infix: InfixRule::Assign,
+                precedence: Precedence::Assignment,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::LessLessEqual,
+            ParseRule {
+                infix: InfixRule::Assign,
+                precedence: Precedence::Assignment,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::GreaterGreaterEqual,
+            ParseRule {
+                infix: InfixRule::Assign,
+                precedence: Precedence::Assignment,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::AmpersandEqual,
+            ParseRule {
+                infix: InfixRule::Assign,
+                precedence: Precedence::Assignment,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::PipeEqual,
+            ParseRule {
+                infix: InfixRule::Assign,
+                precedence: Precedence::Assignment,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::CaretEqual,
+            ParseRule {
+                infix: InfixRule::Assign,
+                precedence: Precedence::Assignment,
+                ..Default::default()
+            },
+        ),
+        (
+            TokenType::PlusPlus,
+            ParseRule {
+                prefix: PrefixRule::Unary,
+                infix: InfixRule::Postfix,
+                precedence: Precedence::Call,
+            },
+        ),
+        (
+            TokenType::MinusMinus,
+            ParseRule {
+                prefix: PrefixRule::Unary,
+                infix: InfixRule::Postfix,
+                precedence: Precedence::Call,
+            },
+        ),
+        (
+            TokenType::Comma,
+            ParseRule {
+                infix: InfixRule::Comma,
+                precedence: Precedence::Comma,
+                ..Default::default()
+            },
+        ),
         (
             TokenType::LeftParen,
             ParseRule {
@@ -212,150 +489,2160 @@ fn get_rule(token_type: &TokenType) -> ParseRule {
     RULES.get(token_type).cloned().unwrap_or_default()
 }
 
+/// Diagnostics that are off by default, named after the gcc flags that enable
+/// the same checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Warning {
+    /// Implicit conversions that may change a value, such as assigning a
+    /// `double` to an `int`.
+    Conversion,
+    /// Reads of a variable before anything is assigned to it.
+    Uninitialized,
+    /// Assignments used as conditions, as in `if (x = 0)`, where `==` was
+    /// most likely meant.
+    Parentheses,
+}
+
+impl FromStr for Warning {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "conversion" => Ok(Warning::Conversion),
+            "uninitialized" => Ok(Warning::Uninitialized),
+            "parentheses" => Ok(Warning::Parentheses),
+            _ => Err(format!(
+                "unknown warning '{}', expected one of conversion, uninitialized, parentheses",
+                s
+            )),
+        }
+    }
+}
+
+// The static type of a compiled expression and the source it spans.
+#[derive(Debug, Clone, PartialEq)]
+struct Expression {
+    ty: Type,
+    info: TokenInfo,
+    // The variable an lvalue designates. Its code is then a single load.
+    lvalue: Option<Slot>,
+    // The array type of an expression that decays to a pointer, for `sizeof`.
+    array: Option<CType>,
+    // Whether it is a simple assignment outside of any parentheses.
+    assignment: bool,
+    // The index of the warning that reading an uninitialized lvalue gave,
+    // which is taken back if the lvalue is only assigned to.
+    uninitialized: Option<usize>,
+}
+
+impl Expression {
+    fn new(ty: Type, info: TokenInfo) -> Self {
+        Self {
+            ty,
+            info,
+            lvalue: None,
+            array: None,
+            assignment: false,
+            uninitialized: None,
+        }
+    }
+}
+
+// Where a variable's value is kept: in a stack slot, or in the globals area
+// for the ones that live as long as the program.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Slot {
+    Stack(usize),
+    Global(usize),
+}
+
+// A name in scope.
+#[derive(Debug, Clone, PartialEq)]
+struct Local {
+    name: String,
+    ty: Type,
+    depth: usize,
+    info: TokenInfo,
+    initialized: bool,
+    slot: Slot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Linkage {
+    Internal,
+    External,
+}
+
+// A struct or union type, keyed by its tag such as "struct point".
+#[derive(Debug, Clone, PartialEq)]
+struct Tag {
+    key: String,
+    ty: CType,
+    depth: usize,
+}
+
+// A variable in the globals area. Those with linkage are shared by every
+// declaration of their name, while `static` locals have none.
+#[derive(Debug, Clone, PartialEq)]
+struct Global {
+    name: String,
+    ty: Type,
+    linkage: Option<Linkage>,
+    info: TokenInfo,
+    // Whether some declaration defines it, rather than only `extern` ones.
+    defined: bool,
+    initialized: bool,
+    used: Option<TokenInfo>,
+}
+
+// The loop a `break` or `continue` leaves, with the jumps each has emitted,
+// to be patched once their targets are known.
+#[derive(Debug, Clone, PartialEq)]
+struct Loop {
+    // The scope depth outside the body. Variables declared deeper are popped
+    // before jumping.
+    depth: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+    // Which variables are set on every path to a `break` or `continue`.
+    initialized: Option<Vec<bool>>,
+}
+
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct Parser {
     tokens: Vec<Token>,
     index: usize,
-    ops: Vec<OpCode>,
+    chunk: Chunk,
+    errors: Vec<Error>,
+    // The names in scope, innermost last. Automatic variables are on the
+    // stack in the same order.
+    locals: Vec<Local>,
+    globals: Vec<Global>,
+    // Struct and union types in scope by tag, innermost last.
+    tags: Vec<Tag>,
+    enabled: Vec<Warning>,
+    warnings: Vec<Error>,
+    // How constant initializers handle signed overflow, as the program does.
+    overflow: Overflow,
+    // How many blocks the statement being parsed is nested in.
+    depth: usize,
+    // Whether an error was reported since the last complete statement.
+    recovering: bool,
+    // The loops the statement being parsed is in, innermost last.
+    loops: Vec<Loop>,
 }
 
 impl Parser {
     pub fn new(tokens: &[Token]) -> Self {
         Self {
-            tokens: tokens.to_vec(),
+            tokens: tokens
+                .iter()
+                .filter(|token| token.r#type != TokenType::Comment)
+                .cloned()
+                .collect(),
             ..Default::default()
         }
     }
 
-    pub fn parse(&mut self) -> Vec<OpCode> {
-        while self.curr().r#type != TokenType::Eof {
-            self.expression();
-        }
-        self.emit_return();
-        self.ops.clone()
+    pub fn with_warning(mut self, warning: Warning) -> Self {
+        self.enabled.push(warning);
+        self
     }
 
-    fn expression(&mut self) {
-        self.parse_precedence(Precedence::Assignment);
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
     }
 
-    fn grouping(&mut self) {
-        self.expression();
-        self.consume(&TokenType::RightParen, "Expect ')' after expression.")
+    pub fn warnings(&self) -> &[Error] {
+        &self.warnings
     }
 
-    fn consume(&mut self, token_type: &TokenType, message: &str) {
-        if self.curr().r#type == *token_type {
-            self.advance();
-            return;
+    pub fn parse(&mut self) -> std::result::Result<Chunk, Vec<Error>> {
+        self.statements(&TokenType::Eof);
+        self.emit_return();
+        // Nothing else is linked in, so an `extern` variable that is used must
+        // be defined here.
+        for global in &self.globals {
+            if let (false, Some(used)) = (global.defined, &global.used) {
+                self.errors.push(
+                    Error::new(
+                        ErrorCode::UndefinedReference,
+                        format!("Undefined reference to '{}'", global.name),
+                        used.clone(),
+                    )
+                    .with_label("declared here", global.info.clone()),
+                );
+            }
+        }
+
+        if self.errors.is_empty() {
+            Ok(self.chunk.clone())
+        } else {
+            Err(self.errors.clone())
         }
-        panic!("{}", message);
     }
 
-    fn binary(&mut self) {
-        let prev = self.prev();
-        let operator_type = prev.r#type;
+    // Parses statements up to `end`, reporting each error and resuming after
+    // it so that every mistake in the list is found.
+    fn statements(&mut self, end: &TokenType) {
+        while self.curr().r#type != *end && self.curr().r#type != TokenType::Eof {
+            let start = self.index;
+            match self.statement() {
+                // A statement that runs up to its own terminator shows that
+                // parsing is back in step, unlike a leftover fragment whose
+                // missing ';' was let through.
+                Ok(()) => {
+                    if let TokenType::Semicolon | TokenType::RightBrace = self.prev().r#type {
+                        self.recovering = false;
+                    }
+                }
+                Err(err) => {
+                    self.errors.push(err);
+                    self.synchronize(start);
+                    self.recovering = true;
+                }
+            }
+        }
+    }
 
-        let rule_precedence = get_rule(&operator_type).precedence + 1;
+    fn statement(&mut self) -> Result<()> {
+        match self.curr().r#type {
+            ref token if token.is_type_specifier() || token.is_storage_class() => {
+                self.declaration()
+            }
+            TokenType::LeftBrace => self.block(),
+            TokenType::KwIf => self.if_statement(),
+            TokenType::KwWhile => self.while_statement(),
+            TokenType::KwDo => self.do_statement(),
+            TokenType::KwFor => self.for_statement(),
+            TokenType::KwBreak | TokenType::KwContinue => self.jump_statement(),
+            TokenType::KwElse => Err(Error::new(
+                ErrorCode::ExpectedExpression,
+                "'else' without a previous 'if'",
+                self.curr_info(),
+            )),
+            TokenType::Semicolon => {
+                self.advance();
+                Ok(())
+            }
+            _ => self.expression_statement(),
+        }
+    }
 
-        self.parse_precedence(rule_precedence);
+    fn block(&mut self) -> Result<()> {
+        self.advance();
+        let open = self.prev().info;
+        self.depth += 1;
+        self.statements(&TokenType::RightBrace);
+        let close = self.consume(&TokenType::RightBrace, "Expect '}' after block.");
+        self.end_scope(self.prev().info);
+        self.depth -= 1;
+        close.map_err(|err| {
+            err.with_note(format!(
+                "unclosed '{{' opened at line {}, col {}",
+                open.line + 1,
+                open.column + 1
+            ))
+        })
+    }
 
-        match operator_type {
-            TokenType::Integer | TokenType::Float => {
-                self.ops.push(OpCode::Constant(prev.value.unwrap()))
+    // Pops the variables declared in the current scope as it ends.
+    fn end_scope(&mut self, info: TokenInfo) {
+        while self
+            .locals
+            .last()
+            .is_some_and(|local| local.depth == self.depth)
+        {
+            if let Some(Local {
+                slot: Slot::Stack(_),
+                ..
+            }) = self.locals.pop()
+            {
+                self.emit_byte(OpCode::Pop, info.clone());
             }
-            TokenType::Plus => self.emit_byte(OpCode::Op(Op::Plus)),
-            TokenType::Minus => self.emit_byte(OpCode::Op(Op::Minus)),
-            TokenType::Star => self.emit_byte(OpCode::Op(Op::Multiply)),
-            TokenType::Slash => self.emit_byte(OpCode::Op(Op::Divide)),
-            TokenType::Eof => self.emit_byte(OpCode::Return),
-            TokenType::LeftParen | TokenType::RightParen | TokenType::Equal => {} // doesn't emit an op
-            TokenType::Greater => self.emit_byte(OpCode::Op(Op::Greater)),
-            TokenType::GreaterEqual => self.emit_byte(OpCode::Op(Op::GreaterEqual)),
-            TokenType::Less => self.emit_byte(OpCode::Op(Op::Less)),
-            TokenType::LessEqual => self.emit_byte(OpCode::Op(Op::LessEqual)),
-            TokenType::EqualEqual => self.emit_byte(OpCode::Op(Op::EqualEqual)),
-            TokenType::BangEqual => self.emit_byte(OpCode::Op(Op::BangEqual)),
-            TokenType::Error => panic!("Error"),
+        }
+        while self.tags.last().is_some_and(|tag| tag.depth == self.depth) {
+            self.tags.pop();
         }
     }
 
-    fn unary(&mut self) {
-        let operator_type = self.prev().r#type;
+    // The `else` goes with the nearest `if`, since the statement after the
+    // condition is parsed in full before looking for one.
+    fn if_statement(&mut self) -> Result<()> {
+        self.advance();
+        let keyword = self.prev().info;
+        self.condition("if")?;
+        let then_jump = self.emit_jump(OpCode::JumpIfFalse(0), keyword.clone());
+        let initialized = self.initialized();
+        self.substatement()?;
+        if self.curr().r#type != TokenType::KwElse {
+            self.patch_jump(then_jump);
+            self.restore(&initialized);
+            return Ok(());
+        }
+        self.advance();
+        let else_jump = self.emit_jump(OpCode::Jump(0), self.prev().info);
+        self.patch_jump(then_jump);
+        let then = self.initialized();
+        self.restore(&initialized);
+        self.substatement()?;
+        self.patch_jump(else_jump);
+        self.merge(&then);
+        Ok(())
+    }
 
-        self.parse_precedence(Precedence::Unary);
+    // The condition is tested before each run of the body, which jumps back
+    // to it when done.
+    fn while_statement(&mut self) -> Result<()> {
+        self.advance();
+        let keyword = self.prev().info;
+        let start = self.chunk.ops.len();
+        self.condition("while")?;
+        let exit_jump = self.emit_jump(OpCode::JumpIfFalse(0), keyword.clone());
+        let initialized = self.initialized();
+        let jumps = self.loop_body()?;
+        self.restore(&initialized);
+        self.patch_jumps(&jumps.continues);
+        self.emit_loop(start, keyword);
+        self.patch_jump(exit_jump);
+        self.patch_jumps(&jumps.breaks);
+        Ok(())
+    }
 
-        match operator_type {
-            TokenType::Minus => self.emit_byte(OpCode::Negate),
-            TokenType::Plus => {}
-            _ => unreachable!(),
+    fn do_statement(&mut self) -> Result<()> {
+        self.advance();
+        let keyword = self.prev().info;
+        let start = self.chunk.ops.len();
+        let jumps = self.loop_body()?;
+        if let Some(initialized) = &jumps.initialized {
+            self.merge(initialized);
         }
+        self.patch_jumps(&jumps.continues);
+        self.consume(&TokenType::KwWhile, "Expect 'while' after 'do' body.")
+            .map_err(|err| err.with_label("to match this 'do'", keyword.clone()))?;
+        self.condition("while")?;
+        let exit_jump = self.emit_jump(OpCode::JumpIfFalse(0), keyword.clone());
+        self.emit_loop(start, keyword);
+        self.patch_jump(exit_jump);
+        self.patch_jumps(&jumps.breaks);
+        self.consume(&TokenType::Semicolon, "Expected ';' after 'do' statement")
     }
 
-    fn parse_precedence(&mut self, precedence: Precedence) {
+    // A variable declared in the first clause is in scope for the rest of
+    // the loop, which is a scope of its own.
+    fn for_statement(&mut self) -> Result<()> {
         self.advance();
-        let prefix_rule = get_rule(&self.prev().r#type).prefix;
+        let keyword = self.prev().info;
+        self.depth += 1;
+        let result = self.for_loop(keyword);
+        self.end_scope(self.prev().info);
+        self.depth -= 1;
+        result
+    }
 
-        match prefix_rule {
-            PrefixRule::Number => self.number(),
-            PrefixRule::Grouping => self.grouping(),
-            PrefixRule::Unary => self.unary(),
-            PrefixRule::None => panic!("Expected expression"),
-            _ => unreachable!(),
+    // The increment is written before the body but runs after it, so its
+    // code is taken back out of the chunk and emitted after the body.
+    fn for_loop(&mut self, keyword: TokenInfo) -> Result<()> {
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'for'.")?;
+        let open = self.prev().info;
+        match self.curr().r#type {
+            TokenType::Semicolon => self.advance(),
+            ref token if token.is_type_specifier() || token.is_storage_class() => {
+                if let TokenType::KwStatic | TokenType::KwExtern = token {
+                    self.errors.push(Error::new(
+                        ErrorCode::InvalidStorageClass,
+                        format!(
+                            "Declaration of non-automatic variable in 'for' loop initial \
+                             declaration ('{}')",
+                            self.curr().lexeme
+                        ),
+                        self.curr_info(),
+                    ));
+                }
+                self.declaration()?;
+            }
+            _ => {
+                let init = self.expression()?;
+                self.emit_byte(OpCode::Pop, init.info);
+                self.consume(
+                    &TokenType::Semicolon,
+                    "Expected ';' after 'for' initializer",
+                )?;
+            }
         }
 
-        while precedence <= get_rule(&self.curr().r#type).precedence {
-            self.advance();
-            let infix_rule = get_rule(&self.prev().r#type).infix;
+        let start = self.chunk.ops.len();
+        let mut exit_jump = None;
+        if self.curr().r#type != TokenType::Semicolon {
+            let condition = self.expression()?;
+            self.check_condition(&condition);
+            exit_jump = Some(self.emit_jump(OpCode::JumpIfFalse(0), keyword.clone()));
+        }
+        self.consume(&TokenType::Semicolon, "Expected ';' after 'for' condition")?;
 
-            match infix_rule {
-                InfixRule::Binary => self.binary(),
-                InfixRule::None => unreachable!(),
-            }
+        let increment_start = self.chunk.ops.len();
+        let initialized = self.initialized();
+        if self.curr().r#type != TokenType::RightParen {
+            let increment = self.expression()?;
+            self.emit_byte(OpCode::Pop, increment.info);
         }
-    }
+        let increment = self.chunk.ops.split_off(increment_start);
+        let spans = self.chunk.spans.split_off(increment_start);
+        self.consume(&TokenType::RightParen, "Expect ')' after 'for' clauses.")
+            .map_err(|err| {
+                err.with_note(format!(
+                    "unclosed '(' opened at line {}, col {}",
+                    open.line + 1,
+                    open.column + 1
+                ))
+            })?;
 
-    fn advance(&mut self) {
-        if self.curr().r#type != TokenType::Error {
-            self.index += 1;
+        self.restore(&initialized);
+        let jumps = self.loop_body()?;
+        self.restore(&initialized);
+        self.patch_jumps(&jumps.continues);
+        self.chunk.ops.extend(increment);
+        self.chunk.spans.extend(spans);
+        self.emit_loop(start, keyword);
+        if let Some(exit_jump) = exit_jump {
+            self.patch_jump(exit_jump);
         }
+        self.patch_jumps(&jumps.breaks);
+        Ok(())
     }
 
-    fn curr(&self) -> Token {
-        self.tokens[self.index].clone()
+    // Parses a loop's body, returning the `break` and `continue` jumps in it
+    // for the caller to patch.
+    fn loop_body(&mut self) -> Result<Loop> {
+        self.loops.push(Loop {
+            depth: self.depth,
+            breaks: vec![],
+            continues: vec![],
+            initialized: None,
+        });
+        let result = self.substatement();
+        let jumps = self.loops.pop().unwrap();
+        result.map(|()| jumps)
     }
 
-    fn prev(&self) -> Token {
-        self.tokens[self.index - 1].clone()
+    // `break` and `continue` leave the variables declared in the loop body
+    // behind, so they are popped first.
+    fn jump_statement(&mut self) -> Result<()> {
+        self.advance();
+        let keyword = self.prev();
+        let Some(depth) = self.loops.last().map(|inner| inner.depth) else {
+            return Err(Error::new(
+                ErrorCode::NotInLoop,
+                format!("'{}' statement not within a loop", keyword.lexeme),
+                keyword.info,
+            ));
+        };
+        self.consume(
+            &TokenType::Semicolon,
+            &format!("Expected ';' after '{}'", keyword.lexeme),
+        )?;
+        let count = self
+            .locals
+            .iter()
+            .filter(|local| local.depth > depth && matches!(local.slot, Slot::Stack(_)))
+            .count();
+        for _ in 0..count {
+            self.emit_byte(OpCode::Pop, keyword.info.clone());
+        }
+        let jump = self.emit_jump(OpCode::Jump(0), keyword.info);
+        let initialized = self.initialized();
+        let inner = self.loops.last_mut().unwrap();
+        inner.initialized = Some(match inner.initialized.take() {
+            Some(flags) => flags
+                .iter()
+                .zip(initialized)
+                .map(|(a, b)| *a && b)
+                .collect(),
+            None => initialized,
+        });
+        match keyword.r#type {
+            TokenType::KwBreak => inner.breaks.push(jump),
+            _ => inner.continues.push(jump),
+        }
+        Ok(())
     }
 
-    fn number(&mut self) {
-        if let (TokenType::Integer | TokenType::Float, Some(val)) =
-            (&self.prev().r#type, &self.prev().value)
-        {
-            self.emit_constant(val.clone())
-        }
+    // Parses the parenthesized condition of a statement, leaving its value on
+    // the stack.
+    fn condition(&mut self, keyword: &str) -> Result<Expression> {
+        self.consume(
+            &TokenType::LeftParen,
+            &format!("Expect '(' after '{}'.", keyword),
+        )?;
+        let open = self.prev().info;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen, "Expect ')' after condition.")
+            .map_err(|err| {
+                err.with_note(format!(
+                    "unclosed '(' opened at line {}, col {}",
+                    open.line + 1,
+                    open.column + 1
+                ))
+            })?;
+        self.check_condition(&condition);
+        Ok(condition)
     }
 
-    fn emit_constant(&mut self, value: Value) {
-        self.emit_byte(OpCode::Constant(value));
+    fn check_condition(&mut self, condition: &Expression) {
+        if condition.assignment && self.enabled.contains(&Warning::Parentheses) {
+            self.warnings.push(
+                Error::new(
+                    ErrorCode::AssignmentAsCondition,
+                    "Suggest parentheses around assignment used as truth value",
+                    condition.info.clone(),
+                )
+                .with_help("use '==' to compare, or wrap the assignment in parentheses")
+                .with_note("enabled by --warn=parentheses"),
+            );
+        }
     }
 
-    fn end_compiler(&mut self) {
-        self.emit_return();
+    // A statement nested in another is a scope of its own even without
+    // braces, as in C99. Only a block can contain declarations, but one that
+    // isn't is still parsed, so that its name doesn't go on to be reported as
+    // undeclared.
+    fn substatement(&mut self) -> Result<()> {
+        let curr = self.curr();
+        if curr.r#type.is_type_specifier() || curr.r#type.is_storage_class() {
+            self.errors.push(
+                Error::new(
+                    ErrorCode::ExpectedExpression,
+                    format!("Expected expression before '{}'", curr.lexeme),
+                    curr.info,
+                )
+                .with_help("a declaration must be inside a block, as in '{ int x; }'"),
+            );
+        }
+        self.depth += 1;
+        let result = self.statement();
+        self.end_scope(self.prev().info);
+        self.depth -= 1;
+        result
     }
 
-    fn emit_return(&mut self) {
-        self.emit_byte(OpCode::Return);
+    // `int x = 1, y;` declares each variable in the current scope. A variable
+    // is in scope from its own declarator on, so its initializer can already
+    // refer to it.
+    fn declaration(&mut self) -> Result<()> {
+        let storage = self.storage_class();
+        let start = self.curr_info();
+        let base = self.specifiers()?;
+        // `struct point { int x; };` only defines the tag.
+        if let (CType::Record(_), TokenType::Semicolon) = (&base, self.curr().r#type) {
+            self.advance();
+            return Ok(());
+        }
+        loop {
+            let (ty, name) =
+                self.declarator(base.clone(), start.clone(), Some("Expect variable name."))?;
+            let name = name.unwrap();
+            let ty = self.variable_type(ty, &name)?;
+            match (storage.as_ref().map(|token| &token.r#type), self.depth) {
+                (None | Some(TokenType::KwAuto | TokenType::KwRegister), 1..) => {
+                    self.local(&name, ty)?
+                }
+                (Some(TokenType::KwAuto | TokenType::KwRegister), 0) => {
+                    let storage = storage.unwrap();
+                    return Err(Error::new(
+                        ErrorCode::InvalidStorageClass,
+                        format!(
+                            "File-scope declaration of '{}' specifies '{}'",
+                            name.lexeme, storage.lexeme
+                        ),
+                        storage.info,
+                    ));
+                }
+                (storage, _) => self.global(storage.cloned(), &name, ty)?,
+            }
+            if self.curr().r#type != TokenType::Comma {
+                break;
+            }
+            self.advance();
+        }
+        self.consume(&TokenType::Semicolon, "Expected ';' after declaration")
+    }
+
+    // Extra storage classes are reported and ignored, so the rest of the
+    // declaration is still parsed with the first.
+    fn storage_class(&mut self) -> Option<Token> {
+        if !self.curr().r#type.is_storage_class() {
+            return None;
+        }
+        self.advance();
+        let storage = self.prev();
+        while self.curr().r#type.is_storage_class() {
+            self.errors.push(
+                Error::new(
+                    ErrorCode::InvalidStorageClass,
+                    "Multiple storage classes in declaration specifiers",
+                    self.curr_info(),
+                )
+                .with_label("first storage class", storage.info.clone()),
+            );
+            self.advance();
+        }
+        Some(storage)
+    }
+
+    // An automatic variable's slot is reserved before its initializer runs,
+    // and it is zero until assigned.
+    fn local(&mut self, name: &Token, ty: Type) -> Result<()> {
+        let slot = Slot::Stack(
+            self.locals
+                .iter()
+                .filter(|local| matches!(local.slot, Slot::Stack(_)))
+                .count(),
+        );
+        self.declare(name, ty, slot)?;
+        self.emit_constant(Value::Int(0).cast(ty), name.info.clone());
+
+        if self.curr().r#type == TokenType::Equal {
+            self.advance();
+            let operator = self.prev().info;
+            let start = self.chunk.ops.len();
+            let value = self.parse_precedence(Precedence::Assignment)?;
+            let constant = self.converted_constant(start, ty);
+            self.convert(value.ty, ty, &value, constant, &operator, "initializing")?;
+            self.emit_set(slot, operator.clone());
+            self.emit_byte(OpCode::Pop, operator);
+        }
+        Ok(())
+    }
+
+    // Variables at file scope, and `static` ones in a block, live as long as
+    // the program. They start out zero unless given a constant initializer.
+    fn global(&mut self, storage: Option<TokenType>, name: &Token, ty: Type) -> Result<()> {
+        let is_extern = storage == Some(TokenType::KwExtern);
+        let index = match storage {
+            Some(TokenType::KwStatic) if self.depth > 0 => self.add_global(name, ty, None),
+            _ => self.link(name, ty, storage)?,
+        };
+        self.declare(name, ty, Slot::Global(index))?;
+
+        if self.curr().r#type != TokenType::Equal {
+            // Without `extern`, this is a definition, initialized to zero.
+            self.globals[index].defined |= !is_extern;
+            return Ok(());
+        }
+        self.advance();
+        let operator = self.prev().info;
+        if is_extern && self.depth > 0 {
+            return Err(Error::new(
+                ErrorCode::InvalidStorageClass,
+                format!("'{}' has both 'extern' and initializer", name.lexeme),
+                operator,
+            ));
+        }
+        self.globals[index].defined = true;
+        let start = self.chunk.ops.len();
+        let value = self.parse_precedence(Precedence::Assignment)?;
+        let constant = self.converted_constant(start, ty);
+        self.convert(value.ty, ty, &value, constant, &operator, "initializing")?;
+        let Some(initial) = self.evaluate(start)? else {
+            return Err(Error::new(
+                ErrorCode::NotConstant,
+                "Initializer element is not constant",
+                value.info,
+            ));
+        };
+
+        let global = &mut self.globals[index];
+        if global.initialized {
+            return Err(Error::new(
+                ErrorCode::Redeclaration,
+                format!("Redefinition of '{}'", name.lexeme),
+                name.info.clone(),
+            )
+            .with_label("previously defined here", global.info.clone()));
+        }
+        global.initialized = true;
+        self.chunk.globals[index] = initial;
+        Ok(())
+    }
+
+    // Every declaration of a name with linkage refers to the same variable,
+    // so they must agree on its type, and on whether it is `static`.
+    fn link(&mut self, name: &Token, ty: Type, storage: Option<TokenType>) -> Result<usize> {
+        let Some(index) = self
+            .globals
+            .iter()
+            .position(|global| global.linkage.is_some() && global.name == name.lexeme)
+        else {
+            let linkage = match storage {
+                Some(TokenType::KwStatic) => Linkage::Internal,
+                _ => Linkage::External,
+            };
+            return Ok(self.add_global(name, ty, Some(linkage)));
+        };
+        let previous = &self.globals[index];
+        let conflict = if previous.ty != ty {
+            Some((
+                format!("Conflicting types for '{}'; have '{}'", name.lexeme, ty),
+                format!("previous declaration has type '{}'", previous.ty),
+            ))
+        } else {
+            let message = match (storage, previous.linkage) {
+                (None, Some(Linkage::Internal)) => Some(format!(
+                    "Non-static declaration of '{}' follows static declaration",
+                    name.lexeme
+                )),
+                (Some(TokenType::KwStatic), Some(Linkage::External)) => Some(format!(
+                    "Static declaration of '{}' follows non-static declaration",
+                    name.lexeme
+                )),
+                _ => None,
+            };
+            message.map(|message| (message, "previous declaration is here".to_string()))
+        };
+        match conflict {
+            Some((message, label)) => {
+                Err(
+                    Error::new(ErrorCode::Redeclaration, message, name.info.clone())
+                        .with_label(label, previous.info.clone()),
+                )
+            }
+            None => Ok(index),
+        }
+    }
+
+    fn add_global(&mut self, name: &Token, ty: Type, linkage: Option<Linkage>) -> usize {
+        self.globals.push(Global {
+            name: name.lexeme.clone(),
+            ty,
+            linkage,
+            info: name.info.clone(),
+            defined: linkage.is_none(),
+            initialized: false,
+            used: None,
+        });
+        self.chunk.globals.push(Value::Int(0).cast(ty));
+        self.globals.len() - 1
+    }
+
+    // Only scalars have values, so only they can be stored in variables.
+    fn variable_type(&self, ty: CType, name: &Token) -> Result<Type> {
+        match ty {
+            CType::Scalar(ty) => Ok(ty),
+            CType::Void => Err(Error::new(
+                ErrorCode::InvalidType,
+                format!("Variable '{}' declared void", name.lexeme),
+                name.info.clone(),
+            )),
+            ty if ty.size().is_none() => Err(Error::new(
+                ErrorCode::IncompleteType,
+                format!("Storage size of '{}' isn't known", name.lexeme),
+                name.info.clone(),
+            )
+            .with_label(format!("'{}' is incomplete", ty), name.info.clone())),
+            ty => Err(Error::new(
+                ErrorCode::InvalidType,
+                format!("Variables of type '{}' are not supported", ty),
+                name.info.clone(),
+            )),
+        }
+    }
+
+    // A name can be declared again in an inner scope, where it shadows the
+    // outer variable, but not twice in the same one unless both declarations
+    // link to the same variable.
+    fn declare(&mut self, name: &Token, ty: Type, slot: Slot) -> Result<()> {
+        if let Some(previous) = self
+            .locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth == self.depth)
+            .find(|local| local.name == name.lexeme)
+        {
+            if previous.slot == slot {
+                return Ok(());
+            }
+            return Err(Error::new(
+                ErrorCode::Redeclaration,
+                format!("Redefinition of '{}'", name.lexeme),
+                name.info.clone(),
+            )
+            .with_label("previously defined here", previous.info.clone()));
+        }
+        self.locals.push(Local {
+            name: name.lexeme.clone(),
+            ty,
+            depth: self.depth,
+            info: name.info.clone(),
+            initialized: matches!(slot, Slot::Global(_)),
+            slot,
+        });
+        Ok(())
+    }
+
+    // The value of every statement is discarded, except that the last one in
+    // the input may leave out its ';' to become the result of the program.
+    fn expression_statement(&mut self) -> Result<()> {
+        let expression = self.expression()?;
+        if self.depth == 0 && self.curr().r#type == TokenType::Eof {
+            return Ok(());
+        }
+        self.emit_byte(OpCode::Pop, expression.info.clone());
+        if self.curr().r#type == TokenType::Semicolon {
+            self.advance();
+            return Ok(());
+        }
+        // Parsing resumes at a guess after an error, so an expression that
+        // then runs into another is most likely a follow-on and not reported.
+        if self.recovering {
+            return Ok(());
+        }
+        Err(Error::new(
+            ErrorCode::ExpectedToken,
+            "Expected ';' after expression",
+            self.past_prev(),
+        )
+        .with_label("expression ends here", expression.info))
+    }
+
+    // Skip to the end of the statement, or to the next token that can begin
+    // an expression, so that a single mistake is reported once instead of
+    // cascading into follow-on errors.
+    fn synchronize(&mut self, start: usize) {
+        if self.index == start {
+            self.advance();
+        }
+        while self.curr().r#type != TokenType::Eof {
+            match self.curr().r#type {
+                // Outside a block, a '}' can't close anything.
+                TokenType::RightBrace if self.depth == 0 => self.advance(),
+                TokenType::LeftBrace | TokenType::RightBrace => return,
+                ref token if token.is_statement_keyword() => return,
+                _ if self.prev().r#type == TokenType::Semicolon => return,
+                ref token if get_rule(token).prefix != PrefixRule::None => return,
+                _ => self.advance(),
+            }
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expression> {
+        self.parse_precedence(Precedence::Comma)
+    }
+
+    fn grouping(&mut self) -> Result<Expression> {
+        let open = self.prev().info;
+        if self.curr().r#type.is_type_specifier() {
+            return self.cast(open);
+        }
+        let inner = self.expression()?;
+        self.consume(&TokenType::RightParen, "Expect ')' after expression.")
+            .map_err(|err| {
+                err.with_note(format!(
+                    "unclosed '(' opened at line {}, col {}",
+                    open.line + 1,
+                    open.column + 1
+                ))
+            })?;
+        Ok(Expression {
+            info: open.merge(&self.prev().info),
+            assignment: false,
+            ..inner
+        })
+    }
+
+    fn cast(&mut self, open: TokenInfo) -> Result<Expression> {
+        let target = self.type_name()?;
+        self.consume(&TokenType::RightParen, "Expect ')' after type name.")?;
+        let cast = open.merge(&self.prev().info);
+        let start = self.chunk.ops.len();
+        let operand = self.parse_precedence(Precedence::Unary)?;
+
+        let ty = match target {
+            CType::Scalar(ty) => ty,
+            CType::Void => {
+                return Err(Error::new(
+                    ErrorCode::InvalidTypeName,
+                    "Cast to 'void' is not supported",
+                    cast,
+                ))
+            }
+            _ => {
+                return Err(Error::new(
+                    ErrorCode::InvalidOperandTypes,
+                    format!("Conversion to non-scalar type '{}' requested", target),
+                    cast,
+                ))
+            }
+        };
+        let valid = match (ty, operand.ty) {
+            (Type::Pointer, from) => !from.is_floating(),
+            (to, Type::Pointer) => to.is_integer(),
+            _ => true,
+        };
+        if !valid {
+            return Err(Error::new(
+                ErrorCode::InvalidOperandTypes,
+                format!("Invalid cast from type '{}' to type '{}'", operand.ty, ty),
+                cast,
+            )
+            .with_label(format!("operand has type '{}'", operand.ty), operand.info));
+        }
+        // A pointer converts to its address, but strings don't have one.
+        let to_address = ty.is_integer() && ty != Type::Bool;
+        if let (true, Some(Value::String(_))) = (to_address, self.constant(start)) {
+            return Err(Error::new(
+                ErrorCode::InvalidOperandTypes,
+                format!("Cast of a string literal to type '{}' is not supported", ty),
+                cast,
+            )
+            .with_label("string literals have no address", operand.info));
+        }
+        if operand.ty != ty {
+            self.emit_byte(OpCode::Cast(ty), cast.clone());
+        }
+        Ok(Expression::new(ty, cast.merge(&operand.info)))
+    }
+
+    // A type name is its specifiers followed by an abstract declarator.
+    fn type_name(&mut self) -> Result<CType> {
+        let start = self.curr_info();
+        let base = self.specifiers()?;
+        let (ty, _) = self.declarator(base, start, None)?;
+        Ok(ty)
+    }
+
+    // Type specifiers may come in any order, as in `long unsigned int`.
+    fn specifiers(&mut self) -> Result<CType> {
+        if let TokenType::KwStruct | TokenType::KwUnion = self.curr().r#type {
+            self.advance();
+            return self.record();
+        }
+        let (first, start) = (self.index, self.curr_info());
+        let mut specifiers = vec![];
+        while self.curr().r#type.is_type_specifier() {
+            self.advance();
+            specifiers.push(self.prev().r#type);
+        }
+        let info = start.merge(&self.prev().info);
+
+        let count = |specifier: TokenType| specifiers.iter().filter(|s| **s == specifier).count();
+        let (signed, unsigned) = (count(TokenType::KwSigned), count(TokenType::KwUnsigned));
+        let (int, longs) = (count(TokenType::KwInt), count(TokenType::KwLong));
+        let rest: Vec<_> = specifiers
+            .iter()
+            .filter(|s| {
+                !matches!(
+                    s,
+                    TokenType::KwSigned
+                        | TokenType::KwUnsigned
+                        | TokenType::KwInt
+                        | TokenType::KwLong
+                )
+            })
+            .collect();
+        let plain = signed + unsigned + int == 0;
+        let ty = match (rest.as_slice(), longs) {
+            _ if signed + unsigned > 1 || int > 1 => None,
+            ([], 0) => Some(Type::Int),
+            ([], 1) => Some(Type::Long),
+            ([], 2) => Some(Type::LongLong),
+            ([TokenType::KwShort], 0) => Some(Type::Short),
+            ([TokenType::KwChar], 0) if int == 0 => Some(Type::Char),
+            ([TokenType::KwBool], 0) if plain => Some(Type::Bool),
+            ([TokenType::KwFloat], 0) if plain => Some(Type::Float),
+            ([TokenType::KwDouble], 0) if plain => Some(Type::Double),
+            ([TokenType::KwDouble], 1) if plain => Some(Type::LongDouble),
+            ([TokenType::KwVoid], 0) if plain => return Ok(CType::Void),
+            _ => None,
+        };
+        let Some(ty) = ty else {
+            let name: Vec<_> = self.tokens[first..self.index]
+                .iter()
+                .map(|token| token.lexeme.as_str())
+                .collect();
+            return Err(Error::new(
+                ErrorCode::InvalidTypeName,
+                format!("Invalid type name '{}'", name.join(" ")),
+                info,
+            ));
+        };
+        Ok(CType::Scalar(if unsigned == 1 {
+            ty.to_unsigned()
+        } else {
+            ty
+        }))
+    }
+
+    // `struct T` names a tag, and `struct T { ... }` defines it too. A tag
+    // that is never defined is an incomplete type.
+    fn record(&mut self) -> Result<CType> {
+        let keyword = self.prev();
+        let kind = match keyword.r#type {
+            TokenType::KwStruct => RecordKind::Struct,
+            _ => RecordKind::Union,
+        };
+        let tag = match self.curr().r#type {
+            TokenType::Identifier => {
+                self.advance();
+                Some(self.prev().lexeme)
+            }
+            _ => None,
+        };
+        let key = tag.as_ref().map(|tag| format!("{} {}", kind, tag));
+        if self.curr().r#type != TokenType::LeftBrace {
+            let Some(key) = key else {
+                return Err(Error::new(
+                    ErrorCode::ExpectedToken,
+                    format!("Expect tag or '{{' after '{}'.", kind),
+                    self.curr_info(),
+                ));
+            };
+            return Ok(self
+                .tags
+                .iter()
+                .rev()
+                .find(|tag| tag.key == key)
+                .map(|tag| tag.ty.clone())
+                .unwrap_or(CType::Record(Record {
+                    kind,
+                    tag,
+                    members: None,
+                })));
+        }
+        self.advance();
+
+        let mut members: Vec<Member> = vec![];
+        while !matches!(self.curr().r#type, TokenType::RightBrace | TokenType::Eof) {
+            let start = self.curr_info();
+            if !self.curr().r#type.is_type_specifier() {
+                return Err(Error::new(
+                    ErrorCode::ExpectedToken,
+                    "Expect member declaration.",
+                    start,
+                ));
+            }
+            let base = self.specifiers()?;
+            loop {
+                let (ty, name) =
+                    self.declarator(base.clone(), start.clone(), Some("Expect member name."))?;
+                let name = name.unwrap();
+                if ty.size().is_none() {
+                    return Err(Error::new(
+                        ErrorCode::IncompleteType,
+                        format!("Member '{}' has incomplete type '{}'", name.lexeme, ty),
+                        name.info,
+                    ));
+                }
+                if members.iter().any(|member| member.name == name.lexeme) {
+                    return Err(Error::new(
+                        ErrorCode::InvalidType,
+                        format!("Duplicate member '{}'", name.lexeme),
+                        name.info,
+                    ));
+                }
+                members.push(Member {
+                    name: name.lexeme,
+                    ty,
+                });
+                if self.curr().r#type != TokenType::Comma {
+                    break;
+                }
+                self.advance();
+            }
+            self.consume(
+                &TokenType::Semicolon,
+                "Expect ';' after member declaration.",
+            )?;
+        }
+        self.consume(&TokenType::RightBrace, "Expect '}' after members.")?;
+
+        let ty = CType::Record(Record {
+            kind,
+            tag,
+            members: Some(members),
+        });
+        if let Some(key) = key {
+            if self
+                .tags
+                .iter()
+                .any(|tag| tag.key == key && tag.depth == self.depth)
+            {
+                return Err(Error::new(
+                    ErrorCode::InvalidType,
+                    format!("Redefinition of '{}'", key),
+                    keyword.info.merge(&self.prev().info),
+                ));
+            }
+            self.tags.push(Tag {
+                key,
+                ty: ty.clone(),
+                depth: self.depth,
+            });
+        }
+        Ok(ty)
+    }
+
+    // Declarators are limited to pointers followed by array bounds, as in
+    // `int *[3]`. Members name the declared identifier while type names
+    // leave it out.
+    fn declarator(
+        &mut self,
+        base: CType,
+        start: TokenInfo,
+        named: Option<&str>,
+    ) -> Result<(CType, Option<Token>)> {
+        let mut ty = base;
+        while self.curr().r#type == TokenType::Star {
+            self.advance();
+            ty = CType::Scalar(Type::Pointer);
+        }
+        let mut name = None;
+        if let Some(message) = named {
+            self.consume(&TokenType::Identifier, message)?;
+            name = Some(self.prev());
+        }
+
+        let mut bounds = vec![];
+        while self.curr().r#type == TokenType::LeftBracket {
+            self.advance();
+            bounds.push(self.array_bound()?);
+            self.consume(&TokenType::RightBracket, "Expect ']' after array size.")?;
+        }
+        if !bounds.is_empty() && ty.size().is_none() {
+            return Err(Error::new(
+                ErrorCode::IncompleteType,
+                format!("Array has incomplete element type '{}'", ty),
+                start.merge(&self.prev().info),
+            ));
+        }
+        for len in bounds.into_iter().rev() {
+            ty = CType::Array(Box::new(ty), len);
+        }
+        Ok((ty, name))
+    }
+
+    // The bound is compiled like any expression, then taken back out of the
+    // chunk and evaluated.
+    fn array_bound(&mut self) -> Result<usize> {
+        let start = self.chunk.ops.len();
+        let bound = self.parse_precedence(Precedence::Conditional)?;
+        let constant = self
+            .evaluate(start)?
+            .filter(|_| bound.ty.is_integer())
+            .and_then(|value| value.as_i128());
+        match constant {
+            Some(len) if len >= 0 => Ok(len as usize),
+            Some(len) => Err(Error::new(
+                ErrorCode::InvalidType,
+                format!("Size of array is negative ({})", len),
+                bound.info,
+            )),
+            None => Err(Error::new(
+                ErrorCode::NotConstant,
+                "Array size is not an integer constant",
+                bound.info,
+            )),
+        }
+    }
+
+    // The operand is compiled only for its type. Its code is then dropped,
+    // so it is never evaluated, and the size becomes a constant.
+    fn sizeof(&mut self) -> Result<Expression> {
+        let keyword = self.prev().info;
+        let next = self.tokens.get(self.index + 1).map(|token| &token.r#type);
+        let (ty, info) = if self.curr().r#type == TokenType::LeftParen
+            && next.is_some_and(|next| next.is_type_specifier())
+        {
+            self.advance();
+            let ty = self.type_name()?;
+            self.consume(&TokenType::RightParen, "Expect ')' after type name.")?;
+            (ty, keyword.merge(&self.prev().info))
+        } else {
+            // The operand is never evaluated, so it neither reads nor
+            // initializes any variable.
+            let (start, locals) = (self.chunk.ops.len(), self.locals.clone());
+            let globals = self.globals.clone();
+            let warnings = self.warnings.len();
+            let operand = self.parse_precedence(Precedence::Unary)?;
+            self.chunk.ops.truncate(start);
+            self.chunk.spans.truncate(start);
+            self.locals = locals;
+            self.globals = globals;
+            let unevaluated = self.warnings.split_off(warnings);
+            self.warnings.extend(
+                unevaluated
+                    .into_iter()
+                    .filter(|warning| warning.code != ErrorCode::Uninitialized),
+            );
+            let ty = operand.array.unwrap_or(CType::Scalar(operand.ty));
+            (ty, keyword.merge(&operand.info))
+        };
+        let Some(size) = ty.size() else {
+            return Err(Error::new(
+                ErrorCode::IncompleteType,
+                format!(
+                    "Invalid application of 'sizeof' to incomplete type '{}'",
+                    ty
+                ),
+                info,
+            ));
+        };
+        // `size_t` is `unsigned long`.
+        self.emit_constant(Value::UnsignedLong(size as u64), info.clone());
+        Ok(Expression::new(Type::UnsignedLong, info))
+    }
+
+    fn offsetof(&mut self) -> Result<Expression> {
+        let keyword = self.prev().info;
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'offsetof'.")?;
+        let start = self.curr_info();
+        let ty = self.type_name()?;
+        let type_info = start.merge(&self.prev().info);
+        self.consume(&TokenType::Comma, "Expect ',' after type name.")?;
+        self.consume(&TokenType::Identifier, "Expect member name.")?;
+        let member = self.prev();
+        self.consume(&TokenType::RightParen, "Expect ')' after member name.")?;
+        let info = keyword.merge(&self.prev().info);
+
+        let offset = match &ty {
+            CType::Record(Record { members: None, .. }) => {
+                return Err(Error::new(
+                    ErrorCode::IncompleteType,
+                    format!("'offsetof' applied to incomplete type '{}'", ty),
+                    type_info,
+                ))
+            }
+            CType::Record(_) => ty.offset_of(&member.lexeme).ok_or_else(|| {
+                Error::new(
+                    ErrorCode::NoSuchMember,
+                    format!("No member named '{}' in '{}'", member.lexeme, ty),
+                    member.info,
+                )
+            })?,
+            _ => {
+                return Err(Error::new(
+                    ErrorCode::InvalidOperandTypes,
+                    format!("'offsetof' requires a struct or union type, have '{}'", ty),
+                    type_info,
+                ))
+            }
+        };
+        self.emit_constant(Value::UnsignedLong(offset as u64), info.clone());
+        Ok(Expression::new(Type::UnsignedLong, info))
+    }
+
+    fn consume(&mut self, token_type: &TokenType, message: &str) -> Result<()> {
+        if self.curr().r#type == *token_type {
+            self.advance();
+            return Ok(());
+        }
+        Err(Error::new(
+            ErrorCode::ExpectedToken,
+            message,
+            self.curr_info(),
+        ))
+    }
+
+    fn binary(&mut self, left: Expression) -> Result<Expression> {
+        let prev = self.prev();
+        let operator_type = prev.r#type;
+
+        let rule_precedence = get_rule(&operator_type).precedence + 1;
+
+        let start = self.chunk.ops.len();
+        let right = self.parse_precedence(rule_precedence)?;
+
+        let op = match operator_type {
+            TokenType::Plus => Op::Plus,
+            TokenType::Minus => Op::Minus,
+            TokenType::Star => Op::Multiply,
+            TokenType::Slash => Op::Divide,
+            TokenType::Percent => Op::Modulo,
+            TokenType::Ampersand => Op::BitAnd,
+            TokenType::Pipe => Op::BitOr,
+            TokenType::Caret => Op::BitXor,
+            TokenType::LessLess => Op::ShiftLeft,
+            TokenType::GreaterGreater => Op::ShiftRight,
+            TokenType::Greater => Op::Greater,
+            TokenType::GreaterEqual => Op::GreaterEqual,
+            TokenType::Less => Op::Less,
+            TokenType::LessEqual => Op::LessEqual,
+            TokenType::EqualEqual => Op::EqualEqual,
+            TokenType::BangEqual => Op::BangEqual,
+            _ => {
+                return Err(Error::new(
+                    ErrorCode::UnexpectedOperator,
+                    format!("Unexpected binary operator {:?}", operator_type),
+                    prev.info,
+                ))
+            }
+        };
+        let ty = self.binary_type(&op, &prev.info, &left, &right)?;
+        if matches!(op, Op::ShiftLeft | Op::ShiftRight) {
+            self.check_shift_count(ty, &right, start)?;
+        }
+        self.emit_byte(OpCode::Op(op), prev.info);
+        Ok(Expression::new(ty, left.info.merge(&right.info)))
+    }
+
+    fn binary_type(
+        &self,
+        op: &Op,
+        operator: &TokenInfo,
+        left: &Expression,
+        right: &Expression,
+    ) -> Result<Type> {
+        let integer_only = matches!(
+            op,
+            Op::Modulo | Op::BitAnd | Op::BitOr | Op::BitXor | Op::ShiftLeft | Op::ShiftRight
+        );
+        // Nothing has an address, so there is no pointer arithmetic or
+        // comparison.
+        let valid = |ty: Type| {
+            if integer_only {
+                ty.is_integer()
+            } else {
+                ty.is_arithmetic()
+            }
+        };
+        if !valid(left.ty) || !valid(right.ty) {
+            return Err(Error::new(
+                ErrorCode::InvalidOperandTypes,
+                format!(
+                    "Invalid operands to binary {} (have '{}' and '{}')",
+                    op, left.ty, right.ty
+                ),
+                operator.clone(),
+            )
+            .with_label(
+                format!("left operand has type '{}'", left.ty),
+                left.info.clone(),
+            )
+            .with_label(
+                format!("right operand has type '{}'", right.ty),
+                right.info.clone(),
+            ));
+        }
+
+        Ok(match op {
+            Op::ShiftLeft | Op::ShiftRight => left.ty.promote(),
+            Op::EqualEqual
+            | Op::BangEqual
+            | Op::Greater
+            | Op::GreaterEqual
+            | Op::Less
+            | Op::LessEqual => Type::Int,
+            _ => left.ty.common(right.ty),
+        })
+    }
+
+    // A shift count that compiled to a single constant is checked against the
+    // width of the promoted left operand now, anything else when evaluated.
+    fn check_shift_count(&self, ty: Type, right: &Expression, start: usize) -> Result<()> {
+        let [OpCode::Constant(constant)] = &self.chunk.ops[start..] else {
+            return Ok(());
+        };
+        let Some(count) = constant.as_i128() else {
+            return Ok(());
+        };
+        let bits = ty.bits() as i128;
+        if (0..bits).contains(&count) {
+            return Ok(());
+        }
+        let message = if count < 0 {
+            format!("Shift count is negative ({})", count)
+        } else {
+            format!("Shift count {} is >= width of type '{}'", count, ty)
+        };
+        Err(
+            Error::new(ErrorCode::ShiftCountOutOfRange, message, right.info.clone()).with_note(
+                format!("the shift count must be between 0 and {}", bits - 1),
+            ),
+        )
+    }
+
+    // Both logical operators evaluate to an `int` 0 or 1, so each operand is
+    // tested with its own jump rather than being left on the stack.
+    fn and(&mut self, left: Expression) -> Result<Expression> {
+        let operator = self.prev().info;
+        let left_jump = self.emit_jump(OpCode::JumpIfFalse(0), operator.clone());
+        let initialized = self.initialized();
+        let right = self.parse_precedence(Precedence::And + 1)?;
+        self.restore(&initialized);
+        let right_jump = self.emit_jump(OpCode::JumpIfFalse(0), operator.clone());
+        self.emit_logical_result(&[left_jump, right_jump], operator);
+        Ok(Expression::new(Type::Int, left.info.merge(&right.info)))
+    }
+
+    fn or(&mut self, left: Expression) -> Result<Expression> {
+        let operator = self.prev().info;
+        let left_jump = self.emit_jump(OpCode::JumpIfFalse(0), operator.clone());
+        let true_jump = self.emit_jump(OpCode::Jump(0), operator.clone());
+        self.patch_jump(left_jump);
+        let initialized = self.initialized();
+        let right = self.parse_precedence(Precedence::Or + 1)?;
+        self.restore(&initialized);
+        let right_jump = self.emit_jump(OpCode::JumpIfFalse(0), operator.clone());
+        self.patch_jump(true_jump);
+        self.emit_logical_result(&[right_jump], operator);
+        Ok(Expression::new(Type::Int, left.info.merge(&right.info)))
+    }
+
+    // Falls through to 1, while each of `false_jumps` lands on 0.
+    fn emit_logical_result(&mut self, false_jumps: &[usize], info: TokenInfo) {
+        self.emit_constant(Value::Int(1), info.clone());
+        let end_jump = self.emit_jump(OpCode::Jump(0), info.clone());
+        self.patch_jumps(false_jumps);
+        self.emit_constant(Value::Int(0), info);
+        self.patch_jump(end_jump);
+    }
+
+    // Both arms are converted to a common type. The first arm is compiled
+    // before the second arm's type is known, so its conversion is emitted as
+    // a placeholder and patched afterwards.
+    fn conditional(&mut self, condition: Expression) -> Result<Expression> {
+        let question = self.prev().info;
+        let else_jump = self.emit_jump(OpCode::JumpIfFalse(0), question.clone());
+        let initialized = self.initialized();
+        let then = self.expression()?;
+        let then_cast = self.chunk.ops.len();
+        self.emit_byte(OpCode::Cast(then.ty), then.info.clone());
+        let end_jump = self.emit_jump(OpCode::Jump(0), question.clone());
+
+        self.consume(&TokenType::Colon, "Expect ':' in conditional expression.")
+            .map_err(|err| err.with_label("to match this '?'", question.clone()))?;
+        self.patch_jump(else_jump);
+        let then_initialized = self.initialized();
+        self.restore(&initialized);
+        let otherwise = self.parse_precedence(Precedence::Conditional)?;
+        self.merge(&then_initialized);
+
+        let ty = match (then.ty, otherwise.ty) {
+            (Type::Pointer, Type::Pointer) => Type::Pointer,
+            (a, b) if a != Type::Pointer && b != Type::Pointer => a.common(b),
+            (a, b) => {
+                return Err(Error::new(
+                    ErrorCode::InvalidOperandTypes,
+                    format!(
+                        "Type mismatch in conditional expression ('{}' and '{}')",
+                        a, b
+                    ),
+                    question,
+                )
+                .with_label(format!("this has type '{}'", a), then.info)
+                .with_label(format!("this has type '{}'", b), otherwise.info))
+            }
+        };
+        self.chunk.ops[then_cast] = OpCode::Cast(ty);
+        if otherwise.ty != ty {
+            self.emit_byte(OpCode::Cast(ty), otherwise.info.clone());
+        }
+        self.patch_jump(end_jump);
+
+        Ok(Expression::new(ty, condition.info.merge(&otherwise.info)))
+    }
+
+    fn variable(&mut self) -> Result<Expression> {
+        let prev = self.prev();
+        let Some(local) = self
+            .locals
+            .iter()
+            .rev()
+            .find(|local| local.name == prev.lexeme)
+        else {
+            return Err(Error::new(
+                ErrorCode::UndeclaredIdentifier,
+                format!("Use of undeclared identifier '{}'", prev.lexeme),
+                prev.info,
+            ));
+        };
+        let (slot, ty) = (local.slot, local.ty);
+        let warnings = self.warnings.len();
+        self.check_initialized(slot, &prev.info);
+        if let Slot::Global(index) = slot {
+            self.globals[index].used.get_or_insert(prev.info.clone());
+        }
+        self.emit_get(slot, prev.info.clone());
+        Ok(Expression {
+            lvalue: Some(slot),
+            uninitialized: (self.warnings.len() > warnings).then_some(warnings),
+            ..Expression::new(ty, prev.info)
+        })
+    }
+
+    fn check_initialized(&mut self, slot: Slot, info: &TokenInfo) {
+        let local = self.locals.iter().rev().find(|local| local.slot == slot);
+        let Some(local) = local.filter(|local| !local.initialized) else {
+            return;
+        };
+        if !self.enabled.contains(&Warning::Uninitialized) {
+            return;
+        }
+        self.warnings.push(
+            Error::new(
+                ErrorCode::Uninitialized,
+                format!("'{}' is used uninitialized", local.name),
+                info.clone(),
+            )
+            .with_label("declared here", local.info.clone())
+            .with_note("enabled by --warn=uninitialized"),
+        );
+    }
+
+    // Which variables in scope have been set, to be restored after code that
+    // might not run, such as the body of an `if` or a loop.
+    fn initialized(&self) -> Vec<bool> {
+        self.locals.iter().map(|local| local.initialized).collect()
+    }
+
+    fn restore(&mut self, initialized: &[bool]) {
+        for (local, initialized) in self.locals.iter_mut().zip(initialized) {
+            local.initialized = *initialized;
+        }
+    }
+
+    // A variable is only set after code with several paths if every path
+    // sets it.
+    fn merge(&mut self, initialized: &[bool]) {
+        for (local, initialized) in self.locals.iter_mut().zip(initialized) {
+            local.initialized &= *initialized;
+        }
+    }
+
+    fn emit_get(&mut self, slot: Slot, info: TokenInfo) {
+        match slot {
+            Slot::Stack(slot) => self.emit_byte(OpCode::GetLocal(slot), info),
+            Slot::Global(index) => self.emit_byte(OpCode::GetGlobal(index), info),
+        }
     }
 
-    fn emit_byte(&mut self, opcode: OpCode) {
-        self.ops.push(opcode);
+    fn emit_set(&mut self, slot: Slot, info: TokenInfo) {
+        match slot {
+            Slot::Stack(slot) => self.emit_byte(OpCode::SetLocal(slot), info),
+            Slot::Global(index) => self.emit_byte(OpCode::SetGlobal(index), info),
+        }
+        if let Some(local) = self
+            .locals
+            .iter_mut()
+            .rev()
+            .find(|local| local.slot == slot)
+        {
+            local.initialized = true;
+        }
+    }
+
+    // Simple assignment converts the value to the type of the variable. A
+    // compound assignment `x op= y` is `x = x op y` with `x` evaluated once.
+    fn assign(&mut self, target: Expression) -> Result<Expression> {
+        let operator = self.prev();
+        let Some(slot) = target.lvalue else {
+            return Err(Error::new(
+                ErrorCode::NotAssignable,
+                "Expression is not assignable",
+                operator.info,
+            )
+            .with_label("cannot assign to this", target.info));
+        };
+        let op = match operator.r#type {
+            TokenType::PlusEqual => Some(Op::Plus),
+            TokenType::MinusEqual => Some(Op::Minus),
+            TokenType::StarEqual => Some(Op::Multiply),
+            TokenType::SlashEqual => Some(Op::Divide),
+            TokenType::PercentEqual => Some(Op::Modulo),
+            TokenType::LessLessEqual => Some(Op::ShiftLeft),
+            TokenType::GreaterGreaterEqual => Some(Op::ShiftRight),
+            TokenType::AmpersandEqual => Some(Op::BitAnd),
+            TokenType::PipeEqual => Some(Op::BitOr),
+            TokenType::CaretEqual => Some(Op::BitXor),
+            _ => None,
+        };
+        let simple = op.is_none();
+        if simple {
+            // The old value isn't needed, so its load is dropped, along with
+            // any warning that it was read uninitialized.
+            self.chunk.ops.pop();
+            self.chunk.spans.pop();
+            if let Some(index) = target.uninitialized {
+                self.warnings.remove(index);
+            }
+        }
+
+        let start = self.chunk.ops.len();
+        let value = self.parse_precedence(Precedence::Assignment)?;
+        let constant = self.converted_constant(start, target.ty);
+        let ty = match op {
+            Some(op) => {
+                let ty = self.binary_type(&op, &operator.info, &target, &value)?;
+                if matches!(op, Op::ShiftLeft | Op::ShiftRight) {
+                    self.check_shift_count(ty, &value, start)?;
+                }
+                self.emit_byte(OpCode::Op(op), operator.info.clone());
+                ty
+            }
+            None => value.ty,
+        };
+
+        let var_ty = target.ty;
+        self.convert(ty, var_ty, &value, constant, &operator.info, "assigning to")?;
+        self.emit_set(slot, operator.info);
+        Ok(Expression {
+            assignment: simple,
+            ..Expression::new(var_ty, target.info.merge(&value.info))
+        })
+    }
+
+    // Takes the code compiled from `start` on back out of the chunk and runs
+    // it, unless it uses variables and so isn't a constant expression.
+    fn evaluate(&mut self, start: usize) -> Result<Option<Value>> {
+        let value = self.fold(start);
+        self.chunk.ops.truncate(start);
+        self.chunk.spans.truncate(start);
+        value
+    }
+
+    // Runs the code compiled from `start` on, leaving it in place, unless it
+    // isn't a constant expression.
+    fn fold(&self, start: usize) -> Result<Option<Value>> {
+        let ops = &self.chunk.ops[start..];
+        if ops.iter().any(|op| {
+            matches!(
+                op,
+                OpCode::GetLocal(_)
+                    | OpCode::SetLocal(_)
+                    | OpCode::GetGlobal(_)
+                    | OpCode::SetGlobal(_)
+            )
+        }) {
+            return Ok(None);
+        }
+        Interpreter::new(&Chunk {
+            ops: ops.to_vec(),
+            spans: self.chunk.spans[start..].to_vec(),
+            ..Default::default()
+        })
+        .with_overflow(self.overflow)
+        .interpret()
     }
 
-    fn emit_bytes(&mut self, bytes: &[OpCode]) {
-        for byte in bytes {
-            self.emit_byte(byte.clone());
+    // The value compiled from `start` on, if it is a single constant.
+    fn constant(&self, start: usize) -> Option<Value> {
+        match &self.chunk.ops[start..] {
+            [OpCode::Constant(constant)] => Some(constant.clone()),
+            _ => None,
         }
     }
+
+    // The constant that a value stored as `to` is checked against. Only a
+    // single constant is trusted not to change in conversion, but any
+    // constant expression that is zero is a null pointer constant.
+    fn converted_constant(&self, start: usize, to: Type) -> Option<Value> {
+        match to {
+            Type::Pointer => self.fold(start).ok().flatten(),
+            _ => self.constant(start),
+        }
+    }
+
+    // Converts a value of type `from` on top of the stack to the type of the
+    // variable it is stored in, as assignment and initialization both do.
+    fn convert(
+        &mut self,
+        from: Type,
+        to: Type,
+        value: &Expression,
+        constant: Option<Value>,
+        operator: &TokenInfo,
+        action: &str,
+    ) -> Result<()> {
+        // An integer constant expression with value zero is the null pointer
+        // constant.
+        let null = to == Type::Pointer
+            && from.is_integer()
+            && constant.as_ref().and_then(Value::as_i128) == Some(0);
+        if null {
+            self.emit_byte(OpCode::Cast(to), operator.clone());
+            return Ok(());
+        }
+        if to.is_arithmetic() != from.is_arithmetic() {
+            return Err(Error::new(
+                ErrorCode::InvalidOperandTypes,
+                format!(
+                    "Incompatible types when {} type '{}' from type '{}'",
+                    action, to, from
+                ),
+                operator.clone(),
+            )
+            .with_label(format!("this has type '{}'", value.ty), value.info.clone()));
+        }
+        if from != to {
+            self.check_conversion(from, to, constant, value.info.clone());
+            self.emit_byte(OpCode::Cast(to), operator.clone());
+        }
+        Ok(())
+    }
+
+    // Warns about an implicit conversion that may change a value, unless the
+    // value is a constant that converts unchanged.
+    fn check_conversion(&mut self, from: Type, to: Type, constant: Option<Value>, info: TokenInfo) {
+        if !self.enabled.contains(&Warning::Conversion) || to.can_represent(from) {
+            return;
+        }
+        if let Some(constant) = constant {
+            if constant.cast(to).cast(constant.ty()) == constant {
+                return;
+            }
+        }
+        self.warnings.push(
+            Error::new(
+                ErrorCode::NarrowingConversion,
+                format!("Conversion from '{}' to '{}' may change value", from, to),
+                info,
+            )
+            .with_note("enabled by --warn=conversion"),
+        );
+    }
+
+    // The operand's value is already on the stack. A second copy is updated
+    // and stored, then popped, leaving the old value as the result.
+    fn postfix(&mut self, operand: Expression) -> Result<Expression> {
+        let operator = self.prev();
+        let slot = self.increment_target(&operator, &operand)?;
+        self.emit_get(slot, operator.info.clone());
+        self.emit_increment(&operator.r#type, operator.info.clone(), operand.ty, slot);
+        self.emit_byte(OpCode::Pop, operator.info.clone());
+        Ok(Expression::new(
+            operand.ty,
+            operand.info.merge(&operator.info),
+        ))
+    }
+
+    fn increment_target(&self, operator: &Token, operand: &Expression) -> Result<Slot> {
+        let kind = match operator.r#type {
+            TokenType::PlusPlus => "increment",
+            _ => "decrement",
+        };
+        let slot = operand.lvalue.ok_or_else(|| {
+            Error::new(
+                ErrorCode::NotAssignable,
+                "Expression is not assignable",
+                operator.info.clone(),
+            )
+            .with_label(
+                format!("lvalue required as {} operand", kind),
+                operand.info.clone(),
+            )
+        })?;
+        if !operand.ty.is_arithmetic() {
+            return Err(Error::new(
+                ErrorCode::InvalidOperandTypes,
+                format!("Invalid operand to {} (have '{}')", kind, operand.ty),
+                operator.info.clone(),
+            )
+            .with_label(
+                format!("operand has type '{}'", operand.ty),
+                operand.info.clone(),
+            ));
+        }
+        Ok(slot)
+    }
+
+    // Adds or subtracts one from the value on top of the stack and stores it
+    // back, converted to the variable's type.
+    fn emit_increment(&mut self, operator: &TokenType, info: TokenInfo, ty: Type, slot: Slot) {
+        let op = match operator {
+            TokenType::PlusPlus => Op::Plus,
+            _ => Op::Minus,
+        };
+        self.emit_constant(Value::Int(1), info.clone());
+        self.emit_byte(OpCode::Op(op), info.clone());
+        if ty.common(Type::Int) != ty {
+            self.emit_byte(OpCode::Cast(ty), info.clone());
+        }
+        self.emit_set(slot, info);
+    }
+
+    // The left operand is evaluated only for its side effects.
+    fn comma(&mut self, left: Expression) -> Result<Expression> {
+        self.emit_byte(OpCode::Pop, self.prev().info);
+        let right = self.parse_precedence(Precedence::Comma + 1)?;
+        Ok(Expression::new(right.ty, left.info.merge(&right.info)))
+    }
+
+    fn unary(&mut self) -> Result<Expression> {
+        let prev = self.prev();
+        let operator_type = prev.r#type.clone();
+
+        let operand = self.parse_precedence(Precedence::Unary)?;
+        let valid = match operator_type {
+            TokenType::Minus | TokenType::Plus => operand.ty.is_arithmetic(),
+            TokenType::Tilde => operand.ty.is_integer(),
+            _ => true,
+        };
+        if !valid {
+            return Err(Error::new(
+                ErrorCode::InvalidOperandTypes,
+                format!(
+                    "Invalid operand to unary {} (have '{}')",
+                    prev.lexeme, operand.ty
+                ),
+                prev.info,
+            )
+            .with_label(format!("operand has type '{}'", operand.ty), operand.info));
+        }
+
+        let ty = match operator_type {
+            TokenType::Minus => {
+                self.emit_byte(OpCode::Negate, prev.info.clone());
+                operand.ty.promote()
+            }
+            TokenType::Plus => {
+                let ty = operand.ty.promote();
+                if ty != operand.ty {
+                    self.emit_byte(OpCode::Cast(ty), prev.info.clone());
+                }
+                ty
+            }
+            TokenType::Bang => {
+                self.emit_byte(OpCode::Not, prev.info.clone());
+                Type::Int
+            }
+            TokenType::PlusPlus | TokenType::MinusMinus => {
+                let slot = self.increment_target(&prev, &operand)?;
+                self.emit_increment(&operator_type, prev.info.clone(), operand.ty, slot);
+                operand.ty
+            }
+            TokenType::Tilde => {
+                self.emit_byte(OpCode::BitNot, prev.info.clone());
+                operand.ty.promote()
+            }
+            _ => {
+                return Err(Error::new(
+                    ErrorCode::UnexpectedOperator,
+                    format!("Unexpected unary operator {:?}", operator_type),
+                    prev.info,
+                ))
+            }
+        };
+        Ok(Expression::new(ty, prev.info.merge(&operand.info)))
+    }
+
+    fn parse_precedence(&mut self, precedence: Precedence) -> Result<Expression> {
+        if self.curr().r#type == TokenType::Eof {
+            return Err(Error::new(
+                ErrorCode::ExpectedExpression,
+                "Expected expression",
+                self.curr_info(),
+            ));
+        }
+        self.advance();
+        let prefix_rule = get_rule(&self.prev().r#type).prefix;
+
+        let mut expression = match prefix_rule {
+            PrefixRule::Number => self.number(),
+            PrefixRule::Literal => self.literal(),
+            PrefixRule::String => self.string(),
+            PrefixRule::Grouping => self.grouping()?,
+            PrefixRule::Unary => self.unary()?,
+            PrefixRule::Variable => self.variable()?,
+            PrefixRule::Sizeof => self.sizeof()?,
+            PrefixRule::Offsetof => self.offsetof()?,
+            _ => {
+                return Err(Error::new(
+                    ErrorCode::ExpectedExpression,
+                    "Expected expression",
+                    self.prev().info,
+                ))
+            }
+        };
+
+        while precedence <= get_rule(&self.curr().r#type).precedence {
+            self.advance();
+            let infix_rule = get_rule(&self.prev().r#type).infix;
+
+            expression = match infix_rule {
+                InfixRule::Binary => self.binary(expression)?,
+                InfixRule::And => self.and(expression)?,
+                InfixRule::Or => self.or(expression)?,
+                InfixRule::Conditional => self.conditional(expression)?,
+                InfixRule::Assign => self.assign(expression)?,
+                InfixRule::Postfix => self.postfix(expression)?,
+                InfixRule::Comma => self.comma(expression)?,
+                InfixRule::None => {
+                    return Err(Error::new(
+                        ErrorCode::ExpectedOperator,
+                        "Expected operator",
+                        self.prev().info,
+                    ))
+                }
+            };
+        }
+        Ok(expression)
+    }
+
+    fn advance(&mut self) {
+        if self.curr().r#type != TokenType::Eof {
+            self.index += 1;
+        }
+    }
+
+    fn curr(&self) -> Token {
+        self.tokens[self.index].clone()
+    }
+
+    fn prev(&self) -> Token {
+        self.tokens[self.index - 1].clone()
+    }
+
+    // Errors at the end of input point just past the last real token rather
+    // than at a blank line after it.
+    fn curr_info(&self) -> TokenInfo {
+        let curr = self.curr();
+        if curr.r#type != TokenType::Eof || self.index == 0 {
+            return curr.info;
+        }
+        self.past_prev()
+    }
+
+    fn past_prev(&self) -> TokenInfo {
+        let prev = self.prev().info;
+        TokenInfo {
+            start: prev.start + prev.length,
+            length: 0,
+            line: prev.line,
+            column: prev.column + prev.length,
+        }
+    }
+
+    fn number(&mut self) -> Expression {
+        let prev = self.prev();
+        if let (TokenType::Integer | TokenType::Float, Some(val)) = (&prev.r#type, prev.value) {
+            self.emit_constant(val, prev.info.clone())
+        }
+        Expression::new(prev.ty.unwrap_or(Type::Int), prev.info)
+    }
+
+    fn literal(&mut self) -> Expression {
+        let prev = self.prev();
+        // `true` and `false` are `bool` constants, as in C23.
+        let value = match prev.r#type {
+            TokenType::True => Value::Bool(true),
+            TokenType::False => Value::Bool(false),
+            // NULL is the null pointer constant, an integer constant zero.
+            TokenType::Null => Value::Int(0),
+            _ => unreachable!(),
+        };
+        let ty = value.ty();
+        self.emit_constant(value, prev.info.clone());
+        Expression::new(ty, prev.info)
+    }
+
+    // Adjacent string literals are concatenated into one, as in translation
+    // phase 6.
+    fn string(&mut self) -> Expression {
+        let prev = self.prev();
+        let mut info = prev.info;
+        let mut string = vec![];
+        // Each token's type is its element type, `int` for a wide string.
+        let mut element = prev.ty.unwrap_or(Type::Char);
+        if let Some(Value::String(bytes)) = prev.value {
+            string.extend(bytes);
+        }
+        while self.curr().r#type == TokenType::String {
+            self.advance();
+            let prev = self.prev();
+            info = info.merge(&prev.info);
+            if let Some(Value::String(bytes)) = prev.value {
+                string.extend(bytes);
+            }
+            if prev.ty == Some(Type::Int) {
+                element = Type::Int;
+            }
+        }
+        // A narrow string holds one char per byte, and a wide one a wchar_t
+        // per character.
+        let len = match element {
+            Type::Int => String::from_utf8_lossy(&string).chars().count(),
+            _ => string.len(),
+        } + 1;
+        self.emit_constant(Value::String(string), info.clone());
+        // Arrays decay to pointers everywhere but in `sizeof`.
+        Expression {
+            array: Some(CType::Array(Box::new(CType::Scalar(element)), len)),
+            ..Expression::new(Type::Pointer, info)
+        }
+    }
+
+    fn emit_constant(&mut self, value: Value, info: TokenInfo) {
+        self.emit_byte(OpCode::Constant(value), info);
+    }
+
+    // Emits a jump with a placeholder offset and returns its index so that
+    // `patch_jump` can point it at the next op once that is known.
+    fn emit_jump(&mut self, jump: OpCode, info: TokenInfo) -> usize {
+        self.emit_byte(jump, info);
+        self.chunk.ops.len() - 1
+    }
+
+    fn patch_jump(&mut self, jump: usize) {
+        let target = self.chunk.ops.len() - jump - 1;
+        match &mut self.chunk.ops[jump] {
+            OpCode::Jump(offset) | OpCode::JumpIfFalse(offset) => *offset = target,
+            op => unreachable!("cannot patch non-jump op '{}'", op),
+        }
+    }
+
+    fn patch_jumps(&mut self, jumps: &[usize]) {
+        for &jump in jumps {
+            self.patch_jump(jump);
+        }
+    }
+
+    // Jumps back to `start`, counting from the op after the loop.
+    fn emit_loop(&mut self, start: usize, info: TokenInfo) {
+        let offset = self.chunk.ops.len() + 1 - start;
+        self.emit_byte(OpCode::Loop(offset), info);
+    }
+
+    fn emit_return(&mut self) {
+        self.emit_byte(OpCode::Return, self.curr_info());
+    }
+
+    fn emit_byte(&mut self, opcode: OpCode, info: TokenInfo) {
+        self.chunk.write(opcode, info);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+    use insta::assert_yaml_snapshot;
+
+    fn errors(source: &str) -> Vec<String> {
+        let tokens = Scanner::new(source.to_string()).scan().unwrap();
+        Parser::new(&tokens)
+            .parse()
+            .unwrap_err()
+            .iter()
+            .map(|err| err.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_reports_every_error() {
+        let errors = errors("(1 + ) * 3\n4 * / 5\n6 + 7\n(8 - 9");
+        assert_eq!(errors.len(), 3);
+        assert_yaml_snapshot!(errors);
+    }
+
+    #[test]
+    fn test_no_cascading_errors() {
+        let errors = errors("1 + * / 2 3");
+        assert_eq!(errors.len(), 1);
+        assert_yaml_snapshot!(errors);
+    }
+
+    #[test]
+    fn test_adjacent_strings() {
+        let tokens = Scanner::new("\"ab\" /* gap */ \"c\"\n  L\"d\"".to_string())
+            .scan()
+            .unwrap();
+        let chunk = Parser::new(&tokens).parse().unwrap();
+        assert_eq!(
+            chunk.ops[0],
+            OpCode::Constant(Value::String(b"abcd".to_vec()))
+        );
+        assert_yaml_snapshot!(chunk.spans[0]);
+    }
+
+    fn ops(source: &str) -> Vec<String> {
+        let tokens = Scanner::new(source.to_string()).scan().unwrap();
+        let chunk = Parser::new(&tokens).parse().unwrap();
+        chunk.ops.iter().map(|op| op.to_string()).collect()
+    }
+
+    #[test]
+    fn test_patch_jump() {
+        let mut parser = Parser::default();
+        let info = TokenInfo::default();
+        let jump = parser.emit_jump(OpCode::JumpIfFalse(0), info.clone());
+        parser.emit_constant(Value::Int(1), info.clone());
+        parser.emit_constant(Value::Int(2), info.clone());
+        parser.patch_jump(jump);
+        assert_eq!(parser.chunk.ops[jump], OpCode::JumpIfFalse(2));
+
+        let jump = parser.emit_jump(OpCode::Jump(0), info);
+        parser.patch_jump(jump);
+        assert_eq!(parser.chunk.ops[jump], OpCode::Jump(0));
+    }
+
+    #[test]
+    fn test_logical_jumps() {
+        assert_yaml_snapshot!(ops("1 && 2"));
+        assert_yaml_snapshot!(ops("1 || 2"));
+    }
+
+    #[test]
+    fn test_if_else_jumps() {
+        assert_yaml_snapshot!(ops("int x; if (x) x = 1; else x = 2;"));
+        assert_yaml_snapshot!(ops("int x; if (x) if (x) x = 1; else x = 2;"));
+    }
+
+    #[test]
+    fn test_loop_jumps() {
+        assert_yaml_snapshot!(ops("int i; while (i < 3) i++;"));
+        assert_yaml_snapshot!(ops(
+            "for (int i = 0; i < 3; i++) { int j; if (i) continue; break; }"
+        ));
+    }
+
+    #[test]
+    fn test_conditional_unifies_arms() {
+        assert_yaml_snapshot!(ops("1 ? 1 : 2.0"));
+    }
+
+    #[test]
+    fn test_assignment_requires_lvalue() {
+        let errors = errors("int a, b;\n3 = 4;\na = 1, b = 2, (a + b)++;\n--a++");
+        assert_eq!(errors.len(), 3);
+        assert_yaml_snapshot!(errors);
+    }
+
+    #[test]
+    fn test_comma_discards_left_operand() {
+        assert_yaml_snapshot!(ops("1, 2, 3"));
+        let errors = errors("1 2 3");
+        assert_eq!(errors.len(), 1);
+        assert_yaml_snapshot!(errors);
+    }
+
+    #[test]
+    fn test_statements_balance_stack() {
+        assert_yaml_snapshot!(ops("1; { 2; ; } 3"));
+    }
+
+    #[test]
+    fn test_missing_semicolon() {
+        let errors = errors("int x;\nx = 1\nx + 1;\n{ 2 }");
+        assert_eq!(errors.len(), 2);
+        assert_yaml_snapshot!(errors);
+    }
+
+    #[test]
+    fn test_recovery_ends_after_a_statement() {
+        let errors = errors("1 + ;\nint a = 1;\na = 2\na = 3;");
+        assert_eq!(errors.len(), 2);
+        assert_yaml_snapshot!(errors);
+    }
+
+    #[test]
+    fn test_locals_resolve_to_slots() {
+        assert_yaml_snapshot!(ops("int x = 1; { int y, x = 2; x + y; } x"));
+    }
+
+    #[test]
+    fn test_redeclaration() {
+        let errors = errors("{ int x; { int x; } int x; }\n{ int y, y; }");
+        assert_eq!(errors.len(), 2);
+        assert_yaml_snapshot!(errors);
+    }
 }
@@ -1,15 +1,23 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    error::{Error, ErrorCode, Result},
     token::{Token, TokenInfo, TokenType},
+    types::Type,
     value::Value,
 };
 
 pub struct Scanner {
     source: Vec<char>,
     index: usize,
+    byte: usize,
+    start: usize,
+    start_byte: usize,
+    start_line: usize,
+    start_column: usize,
     line: usize,
-    line_pos: usize,
+    line_start: usize,
+    comments: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -23,113 +31,557 @@ impl Scanner {
         Self {
             source: source.chars().collect(),
             index: 0,
+            byte: 0,
+            start: 0,
+            start_byte: 0,
+            start_line: 0,
+            start_column: 0,
             line: 0,
-            line_pos: 0,
+            line_start: 0,
+            comments: false,
         }
     }
 
-    pub fn scan(&mut self) -> Vec<Token> {
+    // Keep comments in the token stream as `TokenType::Comment` trivia
+    // instead of discarding them. The parser skips them either way.
+    pub fn with_comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    pub fn scan(&mut self) -> std::result::Result<Vec<Token>, Vec<Error>> {
         let mut tokens = vec![];
+        let mut errors = vec![];
         while !self.is_at_end() {
-            let op = self.consume();
-            match op {
-                '+' | '-' | '*' | '/' | '(' | ')' => {
-                    let info = self.token_info(1);
+            self.mark_start();
+            match self.scan_token() {
+                Ok(Some(token)) => tokens.push(token),
+                Ok(None) => {}
+                Err(err) => errors.push(err),
+            }
+        }
 
-                    tokens.push(Token {
-                        value: None,
-                        info,
-                        r#type: match op {
-                            '+' => TokenType::Plus,
-                            '-' => TokenType::Minus,
-                            '*' => TokenType::Star,
-                            '/' => TokenType::Slash,
-                            '(' => TokenType::LeftParen,
-                            ')' => TokenType::RightParen,
-                            _ => unreachable!(),
-                        },
-                    });
+        self.mark_start();
+        tokens.push(self.token(TokenType::Eof, None));
+
+        if errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn scan_token(&mut self) -> Result<Option<Token>> {
+        let op = self.consume();
+        let token = match op {
+            '/' if self.r#match('/') => return Ok(self.line_comment()),
+            '/' if self.r#match('*') => return self.block_comment(),
+            '+' | '-' | '*' | '/' | '%' | '&' | '|' | '^' => self.operator(),
+            '~' | '?' | ':' | ',' | ';' | '(' | ')' | '{' | '}' | '[' | ']' => {
+                self.token(TokenType::from(op), None)
+            }
+            '>' | '<' | '=' | '!' => self.relational()?,
+            '0'..='9' => self.number()?,
+            '.' if matches!(self.peek(), Some('0'..='9')) => self.number()?,
+            'L' if matches!(self.peek(), Some('\'' | '"')) => {
+                let quote = self.consume();
+                self.literal(quote, true)?
+            }
+            '\'' | '"' => self.literal(op, false)?,
+            'a'..='z' | 'A'..='Z' | '_' => self.identifier(),
+            ' ' | '\t' | '\r' | '\n' => return Ok(None),
+            _ => {
+                return Err(Error::new(
+                    ErrorCode::UnexpectedCharacter,
+                    format!("Unexpected character '{}'", op),
+                    self.token_info(),
+                ))
+            }
+        };
+        Ok(Some(token))
+    }
+
+    fn line_comment(&mut self) -> Option<Token> {
+        while !matches!(self.peek(), None | Some('\n')) {
+            self.consume();
+        }
+        self.comment()
+    }
+
+    fn block_comment(&mut self) -> Result<Option<Token>> {
+        self.consume();
+        loop {
+            match self.peek() {
+                Some('*') if self.peek_next() == Some('/') => {
+                    self.consume();
+                    self.consume();
+                    return Ok(self.comment());
+                }
+                Some(_) => {
+                    self.consume();
                 }
-                '>' | '<' | '=' | '!' => self.relational(&mut tokens),
-                '0'..='9' => tokens.push(self.number()),
-                '\n' => {
-                    self.line += 1;
-                    self.line_pos = 0;
+                None => {
+                    let info = TokenInfo {
+                        length: 2,
+                        ..self.token_info()
+                    };
+                    return Err(Error::new(
+                        ErrorCode::UnterminatedComment,
+                        "Unterminated block comment",
+                        info,
+                    )
+                    .with_note("the comment is still open at the end of the file")
+                    .with_help("close it with '*/'"));
                 }
-                _ => {}
             }
         }
+    }
 
-        tokens.push(Token {
-            r#type: TokenType::Eof,
-            value: None,
-            info: self.token_info(1),
-        });
+    fn comment(&self) -> Option<Token> {
+        self.comments.then(|| self.token(TokenType::Comment, None))
+    }
 
-        tokens
+    // An arithmetic or bitwise operator, possibly doubled (`++`, `&&`) or
+    // followed by '=' for compound assignment.
+    fn operator(&mut self) -> Token {
+        let c = self.prev().unwrap();
+        let mut lexeme = c.to_string();
+        if matches!(c, '+' | '-' | '&' | '|') && self.r#match(c) {
+            self.consume();
+            lexeme.push(c);
+        } else if self.r#match('=') {
+            self.consume();
+            lexeme.push('=');
+        }
+        self.token(TokenType::from(lexeme.as_str()), None)
     }
 
-    fn relational(&mut self, tokens: &mut Vec<Token>) {
+    fn relational(&mut self) -> Result<Token> {
         let c = self.prev().unwrap();
 
-        tokens.push(if self.r#match('=') {
-            let rel_eq = format!("{}=", c);
-            self.index += 1;
-            Token {
-                value: None,
-                r#type: TokenType::from(rel_eq.as_str()),
-                info: self.token_info(2),
+        Ok(if (c == '<' || c == '>') && self.r#match(c) {
+            self.consume();
+            let mut shift = format!("{}{}", c, c);
+            if self.r#match('=') {
+                self.consume();
+                shift.push('=');
             }
+            self.token(TokenType::from(shift.as_str()), None)
+        } else if self.r#match('=') {
+            let rel_eq = format!("{}=", c);
+            self.consume();
+            self.token(TokenType::from(rel_eq.as_str()), None)
         } else {
-            Token {
-                value: None,
-                r#type: TokenType::from(c),
-                info: self.token_info(1),
+            self.token(TokenType::from(c), None)
+        })
+    }
+
+    fn identifier(&mut self) -> Token {
+        while let Some('a'..='z' | 'A'..='Z' | '0'..='9' | '_') = self.peek() {
+            self.consume();
+        }
+
+        let word: String = self.source[self.start..self.index].iter().collect();
+        match TokenType::keyword(&word) {
+            Some(keyword) => self.token(keyword, None),
+            None => self.token(TokenType::Identifier, None),
+        }
+    }
+
+    // Character constants and string literals share their escapes. A narrow
+    // literal holds bytes, so non-ASCII source characters take up several
+    // units, while a wide one holds whole code points.
+    fn literal(&mut self, quote: char, wide: bool) -> Result<Token> {
+        let mut units = vec![];
+        let mut error = None;
+        loop {
+            match self.peek() {
+                Some(c) if c == quote => {
+                    self.consume();
+                    break;
+                }
+                None | Some('\n') => {
+                    let offset = if wide { 1 } else { 0 };
+                    return Err(Error::new(
+                        ErrorCode::UnterminatedLiteral,
+                        format!("Missing terminating {} character", quote),
+                        self.sub_info(offset, 1),
+                    ));
+                }
+                Some('\\') => match self.escape(wide) {
+                    Ok(unit) => units.push(unit),
+                    Err(err) => error = error.or(Some(err)),
+                },
+                Some(_) => {
+                    let c = self.consume();
+                    if wide {
+                        units.push(c as u32);
+                    } else {
+                        units.extend(c.to_string().bytes().map(u32::from));
+                    }
+                }
+            }
+        }
+        if let Some(err) = error {
+            return Err(err);
+        }
+
+        if quote == '"' {
+            let value = if wide {
+                units
+                    .iter()
+                    .map(|&unit| char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect::<String>()
+                    .into_bytes()
+            } else {
+                units.iter().map(|&unit| unit as u8).collect()
+            };
+            // The type of a string literal is that of its elements.
+            let ty = if wide { Type::Int } else { Type::Char };
+            return Ok(Token {
+                ty: Some(ty),
+                ..self.token(TokenType::String, Some(Value::String(value)))
+            });
+        }
+
+        let value = match units[..] {
+            // `char` is signed on x86-64, so '\xff' is -1.
+            [unit] if !wide => unit as u8 as i8 as i64,
+            [unit] => unit as i32 as i64,
+            [] => {
+                return Err(Error::new(
+                    ErrorCode::InvalidCharConstant,
+                    "Empty character constant",
+                    self.token_info(),
+                ))
+            }
+            _ => {
+                return Err(Error::new(
+                    ErrorCode::InvalidCharConstant,
+                    "Multi-character character constant",
+                    self.token_info(),
+                )
+                .with_help("use double quotes for a string literal"))
+            }
+        };
+        // Character constants have type `int` in C, wide ones `wchar_t`,
+        // which is also `int` on x86-64 Linux.
+        Ok(Token {
+            ty: Some(Type::Int),
+            ..self.token(TokenType::Integer, Some(Value::Int(value as i32)))
+        })
+    }
+
+    fn escape(&mut self, wide: bool) -> Result<u32> {
+        let offset = self.index - self.start;
+        self.consume();
+        let max = if wide { u32::MAX } else { u8::MAX as u32 };
+
+        let unit = match self.peek() {
+            Some(c @ ('n' | 'a' | 'b' | 't' | 'f' | 'r' | 'v' | '\\' | '\'' | '"' | '?')) => {
+                self.consume();
+                match c {
+                    'n' => 0x0a,
+                    'a' => 0x07,
+                    'b' => 0x08,
+                    't' => 0x09,
+                    'f' => 0x0c,
+                    'r' => 0x0d,
+                    'v' => 0x0b,
+                    _ => c as u32,
+                }
+            }
+            Some('0'..='7') => {
+                let mut unit = 0;
+                for _ in 0..3 {
+                    match self.peek() {
+                        Some(c @ '0'..='7') => {
+                            self.consume();
+                            unit = unit * 8 + c.to_digit(8).unwrap();
+                        }
+                        _ => break,
+                    }
+                }
+                if unit > max {
+                    return Err(Error::new(
+                        ErrorCode::InvalidEscape,
+                        "Octal escape sequence out of range",
+                        self.span_from(offset),
+                    ));
+                }
+                unit
             }
-        });
+            Some('x') => {
+                self.consume();
+                let mut unit: Option<u32> = Some(0);
+                let mut digits = 0;
+                while let Some(digit) = self.peek().and_then(|c| c.to_digit(16)) {
+                    self.consume();
+                    digits += 1;
+                    unit = unit
+                        .and_then(|unit| unit.checked_mul(16))
+                        .and_then(|unit| unit.checked_add(digit));
+                }
+                if digits == 0 {
+                    return Err(Error::new(
+                        ErrorCode::InvalidEscape,
+                        "\\x used with no following hex digits",
+                        self.span_from(offset),
+                    ));
+                }
+                match unit {
+                    Some(unit) if unit <= max => unit,
+                    _ => {
+                        return Err(Error::new(
+                            ErrorCode::InvalidEscape,
+                            "Hex escape sequence out of range",
+                            self.span_from(offset),
+                        ))
+                    }
+                }
+            }
+            Some(c) if c != '\n' => {
+                self.consume();
+                return Err(Error::new(
+                    ErrorCode::InvalidEscape,
+                    format!("Unknown escape sequence '\\{}'", c),
+                    self.span_from(offset),
+                ));
+            }
+            _ => {
+                return Err(Error::new(
+                    ErrorCode::InvalidEscape,
+                    "Incomplete escape sequence",
+                    self.span_from(offset),
+                ))
+            }
+        };
+        Ok(unit)
     }
 
     fn r#match(&mut self, expected: char) -> bool {
         !(self.is_at_end() || self.peek() != Some(expected))
     }
 
-    fn number(&mut self) -> Token {
-        let mut is_float = false;
-        let mut number = String::new();
-        number.push(self.prev().unwrap());
-
-        while let Some('.' | '0'..='9') = self.peek() {
-            let res = self.consume();
-            number.push(res);
-            if res == '.' && is_float {
-                panic!("Found two decimals in a float");
+    fn number(&mut self) -> Result<Token> {
+        // Take the whole preprocessing number first, as C does, so that
+        // something like `10ux` is reported as one malformed literal rather
+        // than a literal followed by an identifier.
+        loop {
+            match self.peek() {
+                Some('+' | '-') if matches!(self.prev(), Some('e' | 'E' | 'p' | 'P')) => {}
+                Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '.' => {}
+                _ => break,
             }
-            if res == '.' && !is_float {
-                is_float = true;
+            self.consume();
+        }
+
+        let text: String = self.source[self.start..self.index].iter().collect();
+        let lower = text.to_ascii_lowercase();
+        let (ty, value) = if lower.starts_with("0x") {
+            self.integer(&text, 16, 2)?
+        } else if lower.starts_with("0b") {
+            self.integer(&text, 2, 2)?
+        } else if lower.starts_with("0o") {
+            self.integer(&text, 8, 2)?
+        } else if text.contains(['.', 'e', 'E']) {
+            self.floating(&text)?
+        } else if text.starts_with('0') && text.len() > 1 {
+            self.integer(&text, 8, 1)?
+        } else {
+            self.integer(&text, 10, 0)?
+        };
+
+        let r#type = if ty.is_floating() {
+            TokenType::Float
+        } else {
+            TokenType::Integer
+        };
+        Ok(Token {
+            ty: Some(ty),
+            ..self.token(r#type, Some(value))
+        })
+    }
+
+    fn integer(&self, text: &str, radix: u32, prefix: usize) -> Result<(Type, Value)> {
+        let digits_end = text[prefix..]
+            .find(|c: char| !c.is_ascii_hexdigit() || (radix != 16 && !c.is_ascii_digit()))
+            .map_or(text.len(), |end| prefix + end);
+        let digits = &text[prefix..digits_end];
+        let suffix = &text[digits_end..];
+
+        if digits.is_empty() && prefix > 0 && radix != 8 {
+            return Err(Error::new(
+                ErrorCode::MalformedNumber,
+                format!("No digits after '{}'", &text[..prefix]),
+                self.token_info(),
+            ));
+        }
+        if let Some(offset) = digits.find(|c: char| !c.is_digit(radix)) {
+            let digit = &digits[offset..offset + 1];
+            let base = match radix {
+                2 => "binary",
+                8 => "octal",
+                _ => "decimal",
+            };
+            return Err(Error::new(
+                ErrorCode::InvalidDigit,
+                format!("Invalid digit '{}' in {} constant", digit, base),
+                self.sub_info(prefix + offset, 1),
+            ));
+        }
+
+        let (unsigned, long) = match suffix.to_ascii_lowercase().as_str() {
+            "" => (false, 0),
+            "u" => (true, 0),
+            "l" => (false, 1),
+            "ul" | "lu" => (true, 1),
+            "ll" if suffix != "lL" && suffix != "Ll" => (false, 2),
+            "ull" | "llu" if !suffix.contains("lL") && !suffix.contains("Ll") => (true, 2),
+            _ => {
+                return Err(Error::new(
+                    ErrorCode::InvalidSuffix,
+                    format!("Invalid suffix '{}' on integer constant", suffix),
+                    self.sub_info(digits_end, suffix.len()),
+                ))
             }
+        };
+
+        // An unprefixed `0` is lexed as octal with no digits after the prefix.
+        let digits = if digits.is_empty() { "0" } else { digits };
+        let too_large = || {
+            Error::new(
+                ErrorCode::IntegerTooLarge,
+                format!("Integer literal '{}' is too large", text),
+                self.token_info(),
+            )
+        };
+        let value = u64::from_str_radix(digits, radix).map_err(|_| too_large())?;
+
+        // The candidate types in order, from C11 6.4.4.1: decimal constants
+        // only become unsigned when asked to, other bases may do so to fit.
+        let candidates: &[Type] = match (unsigned, long, radix == 10) {
+            (false, 0, true) => &[Type::Int, Type::Long, Type::LongLong],
+            (false, 0, false) => &[
+                Type::Int,
+                Type::UnsignedInt,
+                Type::Long,
+                Type::UnsignedLong,
+                Type::LongLong,
+                Type::UnsignedLongLong,
+            ],
+            (true, 0, _) => &[
+                Type::UnsignedInt,
+                Type::UnsignedLong,
+                Type::UnsignedLongLong,
+            ],
+            (false, 1, true) => &[Type::Long, Type::LongLong],
+            (false, 1, false) => &[
+                Type::Long,
+                Type::UnsignedLong,
+                Type::LongLong,
+                Type::UnsignedLongLong,
+            ],
+            (true, 1, _) => &[Type::UnsignedLong, Type::UnsignedLongLong],
+            (false, _, true) => &[Type::LongLong],
+            (false, _, false) => &[Type::LongLong, Type::UnsignedLongLong],
+            (true, _, _) => &[Type::UnsignedLongLong],
+        };
+
+        match candidates.iter().find(|ty| ty.max_value() >= value as i128) {
+            Some(ty) => Ok((*ty, Value::from_integer(*ty, value as i128))),
+            None => Err(too_large().with_help("add a 'u' suffix to make it unsigned")),
         }
+    }
 
-        let info = self.token_info(number.len());
+    fn floating(&self, text: &str) -> Result<(Type, Value)> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut index = 0;
+        let mut digits = 0;
 
-        self.line_pos += number.len();
+        while index < chars.len() && chars[index].is_ascii_digit() {
+            index += 1;
+            digits += 1;
+        }
+        if index < chars.len() && chars[index] == '.' {
+            index += 1;
+            while index < chars.len() && chars[index].is_ascii_digit() {
+                index += 1;
+                digits += 1;
+            }
+        }
+        if index < chars.len() && chars[index] == '.' {
+            return Err(Error::new(
+                ErrorCode::MalformedNumber,
+                "Found two decimals in a float",
+                self.sub_info(index, 1),
+            )
+            .with_help("remove the extra '.'"));
+        }
+        if digits == 0 {
+            return Err(Error::new(
+                ErrorCode::MalformedNumber,
+                "Floating constant has no digits",
+                self.token_info(),
+            ));
+        }
+        if index < chars.len() && matches!(chars[index], 'e' | 'E') {
+            let exponent = index;
+            index += 1;
+            if index < chars.len() && matches!(chars[index], '+' | '-') {
+                index += 1;
+            }
+            let start = index;
+            while index < chars.len() && chars[index].is_ascii_digit() {
+                index += 1;
+            }
+            if index == start {
+                return Err(Error::new(
+                    ErrorCode::MalformedNumber,
+                    "Exponent has no digits",
+                    self.sub_info(exponent, index - exponent),
+                ));
+            }
+        }
+
+        let number = &text[..index];
+        let suffix = &text[index..];
+        let ty = match suffix {
+            "" => Type::Double,
+            "f" | "F" => Type::Float,
+            "l" | "L" => Type::LongDouble,
+            _ => {
+                return Err(Error::new(
+                    ErrorCode::InvalidSuffix,
+                    format!("Invalid suffix '{}' on floating constant", suffix),
+                    self.sub_info(index, suffix.len()),
+                ))
+            }
+        };
 
-        match is_float {
-            true => Token {
-                r#type: TokenType::Float,
-                value: Some(Value::Float(number.parse().unwrap())),
-                info,
-            },
-            false => Token {
-                r#type: TokenType::Integer,
-                value: Some(Value::Integer(number.parse().unwrap())),
-                info,
-            },
+        // A `float` is parsed directly rather than rounded from a double.
+        let value = match ty {
+            Type::Float => number.parse().map(Value::Float),
+            _ => number.parse().map(|num| Value::from_floating(ty, num)),
         }
+        .map_err(|_| {
+            Error::new(
+                ErrorCode::MalformedNumber,
+                format!("Invalid float literal '{}'", text),
+                self.token_info(),
+            )
+        })?;
+        Ok((ty, value))
     }
 
     fn consume(&mut self) -> char {
         let res = self.source[self.index];
         self.index += 1;
+        self.byte += res.len_utf8();
+        if res == '\n' {
+            self.line += 1;
+            self.line_start = self.index;
+        }
         res
     }
 
@@ -149,10 +601,6 @@ impl Scanner {
         }
     }
 
-    fn is_at_end(&self) -> bool {
-        self.index >= self.source.len()
-    }
-
     fn peek_next(&self) -> Option<char> {
         if self.index + 1 < self.source.len() {
             Some(self.source[self.index + 1])
@@ -161,12 +609,58 @@ impl Scanner {
         }
     }
 
-    fn token_info(&mut self, length: usize) -> TokenInfo {
-        self.line_pos += length;
+    fn is_at_end(&self) -> bool {
+        self.index >= self.source.len()
+    }
+
+    fn token(&self, r#type: TokenType, value: Option<Value>) -> Token {
+        Token {
+            r#type,
+            value,
+            ty: None,
+            lexeme: self.source[self.start..self.index].iter().collect(),
+            info: self.token_info(),
+        }
+    }
+
+    fn mark_start(&mut self) {
+        self.start = self.index;
+        self.start_byte = self.byte;
+        self.start_line = self.line;
+        self.start_column = self.index - self.line_start;
+    }
+
+    // A span covering part of the current token, `offset` characters in.
+    fn sub_info(&self, offset: usize, length: usize) -> TokenInfo {
+        let info = self.token_info();
+        let skipped: usize = self.source[self.start..self.start + offset]
+            .iter()
+            .map(|c| c.len_utf8())
+            .sum();
         TokenInfo {
-            start: self.line_pos,
+            start: info.start + skipped,
             length,
-            line: self.line,
+            line: info.line,
+            column: info.column + offset,
+        }
+    }
+
+    // A span from `offset` characters into the current token up to the
+    // current position.
+    fn span_from(&self, offset: usize) -> TokenInfo {
+        let length = self.source[self.start + offset..self.index]
+            .iter()
+            .map(|c| c.len_utf8())
+            .sum();
+        self.sub_info(offset, length)
+    }
+
+    fn token_info(&self) -> TokenInfo {
+        TokenInfo {
+            start: self.start_byte,
+            length: self.byte - self.start_byte,
+            line: self.start_line,
+            column: self.start_column,
         }
     }
 }
@@ -179,13 +673,110 @@ mod tests {
     #[test]
     fn test_1() {
         let mut scanner = Scanner::new(" 20.0 + 30.0 - 3 ".to_string());
-        assert_yaml_snapshot!(scanner.scan());
+        assert_yaml_snapshot!(scanner.scan().unwrap());
     }
 
     #[test]
     fn test_2() {
         let input = "93367-76920+596894-231722-8350-3517484393530.0-65+710".to_string();
         let mut scanner = Scanner::new(input);
-        assert_yaml_snapshot!(scanner.scan());
+        assert_yaml_snapshot!(scanner.scan().unwrap());
+    }
+
+    #[test]
+    fn test_spans() {
+        let mut scanner = Scanner::new("1 >= 2\n\t(3.5 !=\n  40)".to_string());
+        assert_yaml_snapshot!(scanner.scan().unwrap());
+    }
+
+    #[test]
+    fn test_identifiers_and_keywords() {
+        let input = "int _x1 = while_ + NULL\nunsigned long sizeof true false WHILE".to_string();
+        let mut scanner = Scanner::new(input);
+        assert_yaml_snapshot!(scanner
+            .scan()
+            .unwrap()
+            .iter()
+            .map(|token| (&token.r#type, &token.lexeme))
+            .collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = "1 // one\n/* two\n   lines */ + /**/ 2 / 3".to_string();
+        let mut scanner = Scanner::new(input);
+        assert_yaml_snapshot!(scanner.scan().unwrap());
+    }
+
+    #[test]
+    fn test_comments_as_trivia() {
+        let input = "1 // one\n/* two\n   lines */ + 2".to_string();
+        let mut scanner = Scanner::new(input).with_comments(true);
+        assert_yaml_snapshot!(scanner.scan().unwrap());
+    }
+
+    #[test]
+    fn test_unterminated_comment() {
+        let input = "1 +\n  /* never closed\n2".to_string();
+        let mut scanner = Scanner::new(input);
+        assert_yaml_snapshot!(scanner.scan().unwrap_err());
+    }
+
+    #[test]
+    fn test_numeric_literals() {
+        let input = "0x1F 0XffUL 017 0b101 0o17 0 10u 42l 7LLU 2147483648 0x80000000 \
+                     18446744073709551615u 1e3 1.5E-2f .5 3. 2.5L"
+            .to_string();
+        let mut scanner = Scanner::new(input);
+        assert_yaml_snapshot!(scanner
+            .scan()
+            .unwrap()
+            .iter()
+            .map(|token| (&token.lexeme, &token.ty, &token.value))
+            .collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_malformed_numbers() {
+        let input =
+            "0x 08 0b12 10ux 1e+ 1.5q 4.5.6 9223372036854775808 99999999999999999999".to_string();
+        let mut scanner = Scanner::new(input);
+        assert_yaml_snapshot!(scanner
+            .scan()
+            .unwrap_err()
+            .iter()
+            .map(|err| (err.code, &err.message, err.info.column, err.info.length))
+            .collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_char_and_string_literals() {
+        let input = r#"'a' '\n' '\x41' '\101' '\0' '\xff' L'\xff' "a\tb\"" L"wide" "é""#;
+        let mut scanner = Scanner::new(input.to_string());
+        assert_yaml_snapshot!(scanner
+            .scan()
+            .unwrap()
+            .iter()
+            .map(|token| (&token.r#type, &token.ty, &token.value))
+            .collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_malformed_literals() {
+        let input = r#"'' 'ab' "a\qb" '\x' "\777" 'é' "oops
+'unterminated"#;
+        let mut scanner = Scanner::new(input.to_string());
+        assert_yaml_snapshot!(scanner
+            .scan()
+            .unwrap_err()
+            .iter()
+            .map(|err| (
+                err.code,
+                &err.message,
+                err.info.line,
+                err.info.column,
+                err.info.length
+            ))
+            .collect::<Vec<_>>());
     }
 }
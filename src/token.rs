@@ -1,12 +1,36 @@
 use serde::{Deserialize, Serialize};
 
-use crate::value::Value;
+use crate::{types::Type, value::Value};
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TokenInfo {
+    /// Byte offset of the first character of the token in the source.
     pub start: usize,
+    /// Length of the token in bytes.
     pub length: usize,
+    /// Zero-based line the token starts on.
     pub line: usize,
+    /// Zero-based column, in characters, the token starts at.
+    pub column: usize,
+}
+
+impl TokenInfo {
+    // The smallest span covering both `self` and `other`, positioned at
+    // whichever of the two starts first.
+    pub fn merge(&self, other: &TokenInfo) -> TokenInfo {
+        let (first, second) = if self.start <= other.start {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let end = (first.start + first.length).max(second.start + second.length);
+        TokenInfo {
+            start: first.start,
+            length: end - first.start,
+            line: first.line,
+            column: first.column,
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Eq, PartialOrd, Ord, Hash)]
@@ -17,16 +41,86 @@ pub enum TokenType {
     Minus,
     Star,
     Slash,
+    Percent,
     Error,
     LeftParen,
     RightParen,
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Semicolon,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
     EqualEqual,
     BangEqual,
+    Bang,
+    Tilde,
+    Ampersand,
+    AmpersandAmpersand,
+    Pipe,
+    PipePipe,
+    Caret,
+    LessLess,
+    GreaterGreater,
     Equal,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentEqual,
+    LessLessEqual,
+    GreaterGreaterEqual,
+    AmpersandEqual,
+    PipeEqual,
+    CaretEqual,
+    PlusPlus,
+    MinusMinus,
+    Question,
+    Colon,
+    Comma,
+    Identifier,
+    String,
+    Comment,
+    True,
+    False,
+    Null,
+    KwAuto,
+    KwBool,
+    KwBreak,
+    KwCase,
+    KwChar,
+    KwConst,
+    KwContinue,
+    KwDefault,
+    KwDo,
+    KwDouble,
+    KwElse,
+    KwEnum,
+    KwExtern,
+    KwFloat,
+    KwFor,
+    KwGoto,
+    KwIf,
+    KwInt,
+    KwLong,
+    KwRegister,
+    KwReturn,
+    KwShort,
+    KwSigned,
+    KwSizeof,
+    KwStatic,
+    KwStruct,
+    KwSwitch,
+    KwTypedef,
+    KwUnion,
+    KwUnsigned,
+    KwVoid,
+    KwVolatile,
+    KwWhile,
+    KwOffsetof,
     #[default]
     Eof,
 }
@@ -35,9 +129,95 @@ pub enum TokenType {
 pub struct Token {
     pub r#type: TokenType,
     pub value: Option<Value>,
+    pub ty: Option<Type>,
+    pub lexeme: String,
     pub info: TokenInfo,
 }
 
+impl TokenType {
+    pub fn keyword(word: &str) -> Option<TokenType> {
+        Some(match word {
+            "auto" => TokenType::KwAuto,
+            "_Bool" => TokenType::KwBool,
+            "break" => TokenType::KwBreak,
+            "case" => TokenType::KwCase,
+            "char" => TokenType::KwChar,
+            "const" => TokenType::KwConst,
+            "continue" => TokenType::KwContinue,
+            "default" => TokenType::KwDefault,
+            "do" => TokenType::KwDo,
+            "double" => TokenType::KwDouble,
+            "else" => TokenType::KwElse,
+            "enum" => TokenType::KwEnum,
+            "extern" => TokenType::KwExtern,
+            "float" => TokenType::KwFloat,
+            "for" => TokenType::KwFor,
+            "goto" => TokenType::KwGoto,
+            "if" => TokenType::KwIf,
+            "int" => TokenType::KwInt,
+            "long" => TokenType::KwLong,
+            "register" => TokenType::KwRegister,
+            "return" => TokenType::KwReturn,
+            "short" => TokenType::KwShort,
+            "signed" => TokenType::KwSigned,
+            "sizeof" => TokenType::KwSizeof,
+            "static" => TokenType::KwStatic,
+            "struct" => TokenType::KwStruct,
+            "switch" => TokenType::KwSwitch,
+            "typedef" => TokenType::KwTypedef,
+            "union" => TokenType::KwUnion,
+            "unsigned" => TokenType::KwUnsigned,
+            "void" => TokenType::KwVoid,
+            "volatile" => TokenType::KwVolatile,
+            "while" => TokenType::KwWhile,
+            "offsetof" => TokenType::KwOffsetof,
+            "true" => TokenType::True,
+            "false" => TokenType::False,
+            "NULL" => TokenType::Null,
+            _ => return None,
+        })
+    }
+
+    // The keywords that begin a statement other than a declaration.
+    pub fn is_statement_keyword(&self) -> bool {
+        matches!(
+            self,
+            TokenType::KwIf
+                | TokenType::KwWhile
+                | TokenType::KwDo
+                | TokenType::KwFor
+                | TokenType::KwBreak
+                | TokenType::KwContinue
+        )
+    }
+
+    pub fn is_storage_class(&self) -> bool {
+        matches!(
+            self,
+            TokenType::KwAuto | TokenType::KwRegister | TokenType::KwStatic | TokenType::KwExtern
+        )
+    }
+
+    // The keywords that can begin a type name.
+    pub fn is_type_specifier(&self) -> bool {
+        matches!(
+            self,
+            TokenType::KwBool
+                | TokenType::KwChar
+                | TokenType::KwShort
+                | TokenType::KwInt
+                | TokenType::KwLong
+                | TokenType::KwSigned
+                | TokenType::KwUnsigned
+                | TokenType::KwFloat
+                | TokenType::KwDouble
+                | TokenType::KwVoid
+                | TokenType::KwStruct
+                | TokenType::KwUnion
+        )
+    }
+}
+
 impl From<char> for TokenType {
     fn from(value: char) -> Self {
         match value {
@@ -47,6 +227,20 @@ impl From<char> for TokenType {
             '+' => TokenType::Plus,
             '/' => TokenType::Slash,
             '*' => TokenType::Star,
+            '%' => TokenType::Percent,
+            '!' => TokenType::Bang,
+            '~' => TokenType::Tilde,
+            '?' => TokenType::Question,
+            ':' => TokenType::Colon,
+            ',' => TokenType::Comma,
+            ';' => TokenType::Semicolon,
+            '{' => TokenType::LeftBrace,
+            '}' => TokenType::RightBrace,
+            '[' => TokenType::LeftBracket,
+            ']' => TokenType::RightBracket,
+            '&' => TokenType::Ampersand,
+            '|' => TokenType::Pipe,
+            '^' => TokenType::Caret,
             '>' => TokenType::Greater,
             '<' => TokenType::Less,
             '=' => TokenType::Equal,
@@ -64,6 +258,27 @@ impl From<&str> for TokenType {
             "+" => TokenType::Plus,
             "/" => TokenType::Slash,
             "*" => TokenType::Star,
+            "%" => TokenType::Percent,
+            "&" => TokenType::Ampersand,
+            "|" => TokenType::Pipe,
+            "^" => TokenType::Caret,
+            "&&" => TokenType::AmpersandAmpersand,
+            "||" => TokenType::PipePipe,
+            "=" => TokenType::Equal,
+            "+=" => TokenType::PlusEqual,
+            "-=" => TokenType::MinusEqual,
+            "*=" => TokenType::StarEqual,
+            "/=" => TokenType::SlashEqual,
+            "%=" => TokenType::PercentEqual,
+            "<<=" => TokenType::LessLessEqual,
+            ">>=" => TokenType::GreaterGreaterEqual,
+            "&=" => TokenType::AmpersandEqual,
+            "|=" => TokenType::PipeEqual,
+            "^=" => TokenType::CaretEqual,
+            "++" => TokenType::PlusPlus,
+            "--" => TokenType::MinusMinus,
+            "<<" => TokenType::LessLess,
+            ">>" => TokenType::GreaterGreater,
             "!=" => TokenType::BangEqual,
             "==" => TokenType::EqualEqual,
             ">" => TokenType::Greater,
@@ -0,0 +1,244 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Type {
+    Bool,
+    Char,
+    UnsignedChar,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+    Long,
+    UnsignedLong,
+    LongLong,
+    UnsignedLongLong,
+    Float,
+    Double,
+    LongDouble,
+    Pointer,
+}
+
+// Sizes and ranges follow the LP64 data model used by gcc on x86-64 Linux,
+// where plain `char` is signed.
+impl Type {
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            Type::Bool
+                | Type::Char
+                | Type::UnsignedChar
+                | Type::Short
+                | Type::UnsignedShort
+                | Type::Int
+                | Type::UnsignedInt
+                | Type::Long
+                | Type::UnsignedLong
+                | Type::LongLong
+                | Type::UnsignedLongLong
+        )
+    }
+
+    pub fn is_floating(&self) -> bool {
+        matches!(self, Type::Float | Type::Double | Type::LongDouble)
+    }
+
+    pub fn is_arithmetic(&self) -> bool {
+        self.is_integer() || self.is_floating()
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        matches!(
+            self,
+            Type::Bool
+                | Type::UnsignedChar
+                | Type::UnsignedShort
+                | Type::UnsignedInt
+                | Type::UnsignedLong
+                | Type::UnsignedLongLong
+        )
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Type::Bool | Type::Char | Type::UnsignedChar => 1,
+            Type::Short | Type::UnsignedShort => 2,
+            Type::Int | Type::UnsignedInt | Type::Float => 4,
+            Type::Long
+            | Type::UnsignedLong
+            | Type::LongLong
+            | Type::UnsignedLongLong
+            | Type::Double
+            | Type::Pointer => 8,
+            Type::LongDouble => 16,
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        match self {
+            Type::Bool => 1,
+            _ => self.size() as u32 * 8,
+        }
+    }
+
+    // The range of an integer type.
+    pub fn min_value(&self) -> i128 {
+        if self.is_unsigned() {
+            0
+        } else {
+            -(1 << (self.bits() - 1))
+        }
+    }
+
+    pub fn max_value(&self) -> i128 {
+        if self.is_unsigned() {
+            (1 << self.bits()) - 1
+        } else {
+            (1 << (self.bits() - 1)) - 1
+        }
+    }
+
+    // Bits of precision: the significand of a floating type, or the value
+    // bits of an integer type.
+    fn digits(&self) -> u32 {
+        match self {
+            Type::Float => 24,
+            Type::Double => 53,
+            Type::LongDouble => 64,
+            _ if self.is_unsigned() => self.bits(),
+            _ => self.bits() - 1,
+        }
+    }
+
+    // Whether every value of `other` converts to this type unchanged.
+    pub fn can_represent(&self, other: Type) -> bool {
+        match (self.is_integer(), other.is_integer()) {
+            _ if *self == Type::Pointer || other == Type::Pointer => true,
+            (true, true) => {
+                self.min_value() <= other.min_value() && other.max_value() <= self.max_value()
+            }
+            (true, false) => false,
+            _ => self.digits() >= other.digits(),
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Type::Bool => 0,
+            Type::Char | Type::UnsignedChar => 1,
+            Type::Short | Type::UnsignedShort => 2,
+            Type::Int | Type::UnsignedInt => 3,
+            Type::Long | Type::UnsignedLong => 4,
+            Type::LongLong | Type::UnsignedLongLong => 5,
+            Type::Float => 6,
+            Type::Double => 7,
+            Type::LongDouble => 8,
+            Type::Pointer => 9,
+        }
+    }
+
+    pub fn to_unsigned(self) -> Type {
+        match self {
+            Type::Char => Type::UnsignedChar,
+            Type::Short => Type::UnsignedShort,
+            Type::Int => Type::UnsignedInt,
+            Type::Long => Type::UnsignedLong,
+            Type::LongLong => Type::UnsignedLongLong,
+            _ => self,
+        }
+    }
+
+    // The integer promotions: every type narrower than `int` fits in it.
+    pub fn promote(self) -> Type {
+        match self {
+            Type::Bool | Type::Char | Type::UnsignedChar | Type::Short | Type::UnsignedShort => {
+                Type::Int
+            }
+            _ => self,
+        }
+    }
+
+    // The usual arithmetic conversions (C11 6.3.1.8), giving the type both
+    // operands of a binary operator are converted to.
+    pub fn common(self, other: Type) -> Type {
+        let (a, b) = (self.promote(), other.promote());
+        if a == b {
+            return a;
+        }
+        if !a.is_integer() || !b.is_integer() {
+            return if a.rank() >= b.rank() { a } else { b };
+        }
+        let (signed, unsigned) = match (a.is_unsigned(), b.is_unsigned()) {
+            (false, false) | (true, true) => return if a.rank() >= b.rank() { a } else { b },
+            (false, true) => (a, b),
+            (true, false) => (b, a),
+        };
+        if unsigned.rank() >= signed.rank() {
+            unsigned
+        } else if signed.max_value() >= unsigned.max_value() {
+            signed
+        } else {
+            signed.to_unsigned()
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Type::Bool => "_Bool",
+            Type::Char => "char",
+            Type::UnsignedChar => "unsigned char",
+            Type::Short => "short",
+            Type::UnsignedShort => "unsigned short",
+            Type::Int => "int",
+            Type::UnsignedInt => "unsigned int",
+            Type::Long => "long",
+            Type::UnsignedLong => "unsigned long",
+            Type::LongLong => "long long",
+            Type::UnsignedLongLong => "unsigned long long",
+            Type::Float => "float",
+            Type::Double => "double",
+            Type::LongDouble => "long double",
+            Type::Pointer => "pointer",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usual_arithmetic_conversions() {
+        assert_eq!(Type::Char.common(Type::Char), Type::Int);
+        assert_eq!(Type::Bool.common(Type::UnsignedShort), Type::Int);
+        assert_eq!(Type::Int.common(Type::UnsignedInt), Type::UnsignedInt);
+        assert_eq!(Type::UnsignedInt.common(Type::Long), Type::Long);
+        assert_eq!(
+            Type::Long.common(Type::UnsignedLongLong),
+            Type::UnsignedLongLong
+        );
+        assert_eq!(
+            Type::LongLong.common(Type::UnsignedLong),
+            Type::UnsignedLongLong
+        );
+        assert_eq!(Type::UnsignedLong.common(Type::Float), Type::Float);
+        assert_eq!(Type::Float.common(Type::Double), Type::Double);
+    }
+
+    #[test]
+    fn test_can_represent() {
+        assert!(Type::Int.can_represent(Type::Short));
+        assert!(Type::Long.can_represent(Type::UnsignedInt));
+        assert!(!Type::UnsignedInt.can_represent(Type::Int));
+        assert!(!Type::Char.can_represent(Type::Int));
+        assert!(!Type::Int.can_represent(Type::Double));
+        assert!(Type::Double.can_represent(Type::Int));
+        assert!(!Type::Float.can_represent(Type::Int));
+        assert!(!Type::Double.can_represent(Type::Long));
+        assert!(!Type::Float.can_represent(Type::Double));
+    }
+}
@@ -2,22 +2,55 @@ use std::fmt;
 
 use serde::{Deserialize, Serialize};
 
+use crate::types::Type;
+
+// One variant per C type, so that every value carries its own width and
+// signedness.
 #[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum Value {
-    Float(f64),
-    Integer(i64),
     Bool(bool),
+    Char(i8),
+    UnsignedChar(u8),
+    Short(i16),
+    UnsignedShort(u16),
+    Int(i32),
+    UnsignedInt(u32),
+    Long(i64),
+    UnsignedLong(u64),
+    LongLong(i64),
+    UnsignedLongLong(u64),
+    Float(f32),
+    Double(f64),
+    // x87 extended precision isn't available, so `long double` is computed
+    // with the precision of a double.
+    LongDouble(f64),
+    // A narrow string's bytes, or the UTF-8 encoding of a wide string.
+    String(Vec<u8>),
+    // A pointer made from an integer, which has nothing to point at.
+    Pointer(u64),
 }
 
 impl From<f64> for Value {
     fn from(value: f64) -> Self {
-        Value::Float(value)
+        Value::Double(value)
     }
 }
 
-impl From<i64> for Value {
-    fn from(value: i64) -> Self {
-        Value::Integer(value)
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Int(value)
+    }
+}
+
+// Truncates toward zero the way x86-64 does. Values out of range are undefined
+// in C, and give the "integer indefinite" minimum of the instruction's width,
+// which is 32 bits for types up to `int` and 64 bits for wider ones.
+fn truncate(num: f64, max: i128) -> i128 {
+    let num = num.trunc();
+    if num >= (-max - 1) as f64 && num < (max + 1) as f64 {
+        num as i128
+    } else {
+        -max - 1
     }
 }
 
@@ -25,17 +58,200 @@ impl fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Value::Float(num) => f.write_fmt(format_args!("{}", num)),
-            Value::Integer(num) => f.write_fmt(format_args!("{}", num)),
-            Value::Bool(b) => f.write_str(&b.to_string()),
+            Value::Double(num) | Value::LongDouble(num) => f.write_fmt(format_args!("{}", num)),
+            Value::String(bytes) => match std::str::from_utf8(bytes) {
+                Ok(s) => f.write_fmt(format_args!("{:?}", s)),
+                Err(_) => f.write_fmt(format_args!("\"{}\"", bytes.escape_ascii())),
+            },
+            Value::Pointer(address) => f.write_fmt(format_args!("{:#x}", address)),
+            _ => f.write_fmt(format_args!("{}", self.as_i128().unwrap())),
         }
     }
 }
 
 impl Value {
+    pub fn ty(&self) -> Type {
+        match self {
+            Value::Bool(_) => Type::Bool,
+            Value::Char(_) => Type::Char,
+            Value::UnsignedChar(_) => Type::UnsignedChar,
+            Value::Short(_) => Type::Short,
+            Value::UnsignedShort(_) => Type::UnsignedShort,
+            Value::Int(_) => Type::Int,
+            Value::UnsignedInt(_) => Type::UnsignedInt,
+            Value::Long(_) => Type::Long,
+            Value::UnsignedLong(_) => Type::UnsignedLong,
+            Value::LongLong(_) => Type::LongLong,
+            Value::UnsignedLongLong(_) => Type::UnsignedLongLong,
+            Value::Float(_) => Type::Float,
+            Value::Double(_) => Type::Double,
+            Value::LongDouble(_) => Type::LongDouble,
+            Value::String(_) | Value::Pointer(_) => Type::Pointer,
+        }
+    }
+
+    // Every integer type fits in an `i128`, which leaves room to compute
+    // exact results before they are checked against the result type.
+    pub fn as_i128(&self) -> Option<i128> {
+        Some(match *self {
+            Value::Bool(b) => b as i128,
+            Value::Char(num) => num as i128,
+            Value::UnsignedChar(num) => num as i128,
+            Value::Short(num) => num as i128,
+            Value::UnsignedShort(num) => num as i128,
+            Value::Int(num) => num as i128,
+            Value::UnsignedInt(num) => num as i128,
+            Value::Long(num) | Value::LongLong(num) => num as i128,
+            Value::UnsignedLong(num) | Value::UnsignedLongLong(num) => num as i128,
+            _ => return None,
+        })
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Float(num) => Some(num as f64),
+            Value::Double(num) | Value::LongDouble(num) => Some(num),
+            _ => self.as_i128().map(|num| num as f64),
+        }
+    }
+
+    // Builds an integer value, keeping the low bits that fit the type as
+    // gcc does for every out-of-range conversion.
+    pub fn from_integer(ty: Type, num: i128) -> Value {
+        match ty {
+            Type::Bool => Value::Bool(num != 0),
+            Type::Char => Value::Char(num as i8),
+            Type::UnsignedChar => Value::UnsignedChar(num as u8),
+            Type::Short => Value::Short(num as i16),
+            Type::UnsignedShort => Value::UnsignedShort(num as u16),
+            Type::Int => Value::Int(num as i32),
+            Type::UnsignedInt => Value::UnsignedInt(num as u32),
+            Type::Long => Value::Long(num as i64),
+            Type::UnsignedLong => Value::UnsignedLong(num as u64),
+            Type::LongLong => Value::LongLong(num as i64),
+            Type::UnsignedLongLong => Value::UnsignedLongLong(num as u64),
+            Type::Float | Type::Double | Type::LongDouble => Value::from_floating(ty, num as f64),
+            Type::Pointer => Value::Pointer(num as u64),
+        }
+    }
+
+    pub fn from_floating(ty: Type, num: f64) -> Value {
+        match ty {
+            Type::Float => Value::Float(num as f32),
+            Type::Double => Value::Double(num),
+            Type::LongDouble => Value::LongDouble(num),
+            Type::UnsignedLong | Type::UnsignedLongLong if num >= 9223372036854775808.0 => {
+                Value::from_integer(
+                    ty,
+                    (truncate(num - 9223372036854775808.0, i64::MAX as i128) as u64 ^ 1 << 63)
+                        as i128,
+                )
+            }
+            Type::UnsignedInt
+            | Type::Long
+            | Type::UnsignedLong
+            | Type::LongLong
+            | Type::UnsignedLongLong => Value::from_integer(ty, truncate(num, i64::MAX as i128)),
+            _ => Value::from_integer(ty, truncate(num, i32::MAX as i128)),
+        }
+    }
+
+    // Conversion as if by assignment. Anything converted to `_Bool` is
+    // compared against zero rather than truncated.
+    pub fn cast(&self, ty: Type) -> Value {
+        if ty == Type::Bool {
+            return Value::Bool(!self.is_falsey());
+        }
+        if let (Value::Pointer(address), true) = (self, ty.is_integer()) {
+            return Value::from_integer(ty, *address as i128);
+        }
+        match (self.as_i128(), self.as_f64()) {
+            (Some(num), _) => Value::from_integer(ty, num),
+            (None, Some(num)) => Value::from_floating(ty, num),
+            (None, None) => self.clone(),
+        }
+    }
+
+    // Scalars are false when they compare equal to zero. A string is a
+    // pointer to its first element and never null.
     pub fn is_falsey(&self) -> bool {
         match self {
-            Value::Bool(b) => *b,
-            _ => false,
+            Value::String(_) => false,
+            Value::Pointer(address) => *address == 0,
+            _ => self.as_f64() == Some(0.0),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truthiness() {
+        assert!(Value::Int(0).is_falsey());
+        assert!(Value::Double(0.0).is_falsey());
+        assert!(Value::Double(-0.0).is_falsey());
+        assert!(Value::Bool(false).is_falsey());
+        assert!(!Value::Int(-1).is_falsey());
+        assert!(!Value::Double(0.1).is_falsey());
+        assert!(!Value::Double(f64::NAN).is_falsey());
+        assert!(!Value::Bool(true).is_falsey());
+        assert!(!Value::String(vec![]).is_falsey());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Value::Bool(true).to_string(), "1");
+        assert_eq!(Value::Bool(false).to_string(), "0");
+        assert_eq!(Value::Char(-1).to_string(), "-1");
+        assert_eq!(Value::Pointer(16).to_string(), "0x10");
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(Value::Int(200).cast(Type::Char), Value::Char(-56));
+        assert_eq!(
+            Value::Int(-1).cast(Type::UnsignedInt),
+            Value::UnsignedInt(u32::MAX)
+        );
+        assert_eq!(Value::Long(1 << 32).cast(Type::Int), Value::Int(0));
+        assert_eq!(Value::Double(-2.9).cast(Type::Int), Value::Int(-2));
+        assert_eq!(Value::Double(0.5).cast(Type::Bool), Value::Bool(true));
+        assert_eq!(Value::Int(256).cast(Type::Bool), Value::Bool(true));
+        assert_eq!(Value::Double(0.1).cast(Type::Float), Value::Float(0.1));
+        assert_eq!(
+            Value::UnsignedLong(u64::MAX).cast(Type::Double),
+            Value::Double(18446744073709551615.0)
+        );
+        assert_eq!(Value::Int(0).cast(Type::Pointer), Value::Pointer(0));
+        assert_eq!(Value::Pointer(5).cast(Type::Long), Value::Long(5));
+        assert_eq!(Value::Pointer(300).cast(Type::Char), Value::Char(44));
+    }
+
+    #[test]
+    fn test_out_of_range_floating_conversions() {
+        assert_eq!(Value::Double(1e10).cast(Type::Int), Value::Int(i32::MIN));
+        assert_eq!(
+            Value::Double(f64::NAN).cast(Type::Int),
+            Value::Int(i32::MIN)
+        );
+        assert_eq!(
+            Value::Double(300.5).cast(Type::UnsignedChar),
+            Value::UnsignedChar(44)
+        );
+        assert_eq!(
+            Value::Double(-1.0).cast(Type::UnsignedInt),
+            Value::UnsignedInt(u32::MAX)
+        );
+        assert_eq!(Value::Double(1e19).cast(Type::Long), Value::Long(i64::MIN));
+        assert_eq!(
+            Value::Double(1e19).cast(Type::UnsignedLong),
+            Value::UnsignedLong(10000000000000000000)
+        );
+        assert_eq!(
+            Value::Double(1e20).cast(Type::UnsignedLong),
+            Value::UnsignedLong(0)
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{fs, process::Output};
+use std::{path::Path, process::Output};
 
 use assert_cmd::cargo::CommandCargoExt;
 use insta::{assert_yaml_snapshot, glob};
@@ -12,31 +12,97 @@ struct TestOutput {
     stderr: Vec<String>,
 }
 
+fn run(path: &Path, flags: &[&str]) -> TestOutput {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    let root = env!("CARGO_MANIFEST_DIR");
+    let path = path.strip_prefix(root).unwrap();
+
+    let Output {
+        status,
+        stdout,
+        stderr,
+    } = cmd
+        .current_dir(root)
+        .args(flags)
+        .arg(path)
+        .output()
+        .unwrap();
+
+    TestOutput {
+        status: status.code().unwrap(),
+        stdout: String::from_utf8_lossy(&stdout)
+            .to_string()
+            .lines()
+            .map(|x| x.to_owned())
+            .collect(),
+        stderr: String::from_utf8_lossy(&stderr)
+            .to_string()
+            .lines()
+            .map(|x| x.to_owned())
+            .collect(),
+    }
+}
+
 #[test]
 fn reference_files() {
     glob!("../test-files/", "**/**", |path| {
-        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
-
-        let Output {
-            status,
-            stdout,
-            stderr,
-        } = cmd.arg(path).output().unwrap();
-
-        let test_output = TestOutput {
-            status: status.code().unwrap(),
-            stdout: String::from_utf8_lossy(&stdout)
-                .to_string()
-                .lines()
-                .map(|x| x.to_owned())
-                .collect(),
-            stderr: String::from_utf8_lossy(&stderr)
-                .to_string()
-                .lines()
-                .map(|x| x.to_owned())
-                .collect(),
-        };
-
-        assert_yaml_snapshot!(test_output);
+        assert_yaml_snapshot!(run(path, &[]));
+    });
+}
+
+#[test]
+fn overflow_modes() {
+    for mode in ["trap", "wrap", "saturate"] {
+        let flag = format!("--overflow={}", mode);
+        glob!("../test-files/", "overflow/*", |path| {
+            assert_yaml_snapshot!(mode, run(path, &[&flag]));
+        });
+    }
+}
+
+#[test]
+fn json_errors() {
+    glob!("../test-files/", "errors/*", |path| {
+        assert_yaml_snapshot!("json", run(path, &["--error-format=json"]));
+    });
+}
+
+#[test]
+fn conversion_warnings() {
+    glob!("../test-files/", "conversion/*", |path| {
+        assert_yaml_snapshot!("warn", run(path, &["--warn=conversion"]));
+    });
+}
+
+// Folding constants must not change what any file prints, in any mode.
+#[test]
+fn optimized_matches_unoptimized() {
+    for mode in ["trap", "wrap", "saturate"] {
+        let flag = format!("--overflow={}", mode);
+        glob!("../test-files/", "**/**", |path| {
+            let folded = run(path, &[&flag, "--optimize=on"]);
+            let unfolded = run(path, &[&flag, "--optimize=off"]);
+            assert_eq!(
+                (folded.status, &folded.stdout, &folded.stderr),
+                (unfolded.status, &unfolded.stdout, &unfolded.stderr),
+                "{} with {}",
+                path.display(),
+                flag
+            );
+        });
+    }
+}
+
+#[test]
+fn uninitialized_warnings() {
+    glob!("../test-files/", "uninitialized/*", |path| {
+        assert_yaml_snapshot!("warn", run(path, &["--warn=uninitialized"]));
+    });
+}
+
+#[test]
+fn parentheses_warnings() {
+    glob!("../test-files/", "parentheses/*", |path| {
+        assert_yaml_snapshot!("warn", run(path, &["--warn=parentheses"]));
     });
 }